@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::prelude::*;
+use std::hint::black_box;
+
+use qualified_immunity::player_attack::{SEEKER_CANDIDATES, SEEKER_SIGHT};
+use qualified_immunity::spatial::SpatialIndex;
+
+// A big swarm spread over the whole vessel, and enough clones hunting it.
+const VIRUSES: usize = 5000;
+const SEEKERS: usize = 200;
+
+fn scattered(count: usize, rng: &mut StdRng) -> Vec<(Entity, Vec2)> {
+    (0..count)
+        .map(|i| {
+            let position = Vec2::new(
+                rng.random_range(-1500.0..1500.0),
+                rng.random_range(-800.0..800.0),
+            );
+            (Entity::from_raw(i as u32), position)
+        })
+        .collect()
+}
+
+// How `select_virus` used to pick: shuffle every virus, then use the stopping
+// rule to take one that's relatively close.
+fn select_by_scanning(
+    viruses: &mut [(Entity, Vec2)],
+    seeker: Vec2,
+    rng: &mut StdRng,
+) -> Option<Entity> {
+    let mut min_dist = f32::MAX;
+    let mut closest_target = None;
+    viruses.shuffle(rng);
+    for (i, (candidate, position)) in viruses.iter().enumerate() {
+        let dist = seeker.distance(*position);
+        if dist < min_dist {
+            if (i as f32 / viruses.len() as f32) < 0.37 {
+                min_dist = dist;
+            } else {
+                closest_target = Some(*candidate);
+            }
+        }
+    }
+    closest_target
+}
+
+// How it picks now, rebuilding the index first like every step does.
+fn select_by_index(index: &SpatialIndex<()>, seeker: Vec2, rng: &mut StdRng) -> Option<Entity> {
+    let mut candidates = index.within_radius(seeker, SEEKER_SIGHT);
+    if candidates.is_empty() {
+        candidates = index.nearest(seeker, SEEKER_CANDIDATES);
+    }
+    candidates.choose(rng).map(|&(entity, _)| entity)
+}
+
+fn targeting(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(2);
+    let mut viruses = scattered(VIRUSES, &mut rng);
+    let seekers = scattered(SEEKERS, &mut rng);
+
+    let mut group = c.benchmark_group(format!("{SEEKERS} seekers among {VIRUSES} viruses"));
+    group.bench_function("scanning", |b| {
+        b.iter(|| {
+            for &(_, seeker) in &seekers {
+                black_box(select_by_scanning(&mut viruses, seeker, &mut rng));
+            }
+        })
+    });
+    group.bench_function("spatial index", |b| {
+        b.iter(|| {
+            let mut index = SpatialIndex::default();
+            for &(entity, position) in &viruses {
+                index.insert(entity, position);
+            }
+            for &(_, seeker) in &seekers {
+                black_box(select_by_index(&index, seeker, &mut rng));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, targeting);
+criterion_main!(benches);
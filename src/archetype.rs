@@ -0,0 +1,256 @@
+use avian2d::prelude::*;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::enemy::Hostile;
+use crate::genome::Genome;
+use crate::movement::{Speed, TurnSpeed};
+
+// Loads enemy archetypes from `assets/enemies`. Edits to the files are picked
+// up while the game runs when it is built with `--features dev`.
+pub struct ArchetypePlugin;
+
+impl Plugin for ArchetypePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemyRegistry>();
+        // Headless apps have no assets and make do with the built-in archetypes.
+        app.add_systems(
+            Startup,
+            load_archetypes.run_if(resource_exists::<AssetServer>),
+        )
+        .add_systems(
+            Update,
+            update_registry.run_if(resource_exists::<ArchetypeHandles>),
+        );
+    }
+
+    // Done once every plugin is in, so it doesn't matter whether the asset
+    // plugins were added before this one or after.
+    fn finish(&self, app: &mut App) {
+        if app.world().contains_resource::<AssetServer>() {
+            app.init_asset::<EnemyArchetype>()
+                .init_asset_loader::<ArchetypeLoader>();
+        }
+    }
+}
+
+// Listed one by one, the web build can't look inside folders.
+const ARCHETYPE_FILES: [&str; 2] = ["enemies/virus.ron", "enemies/bacteria.ron"];
+
+// The same files, built in for when there are no assets to load them from.
+const BUILT_IN_ARCHETYPES: [&str; 2] = [
+    include_str!("../assets/enemies/virus.ron"),
+    include_str!("../assets/enemies/bacteria.ron"),
+];
+
+pub const VIRUS: &str = "virus";
+pub const BACTERIA: &str = "bacteria";
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum ColliderShape {
+    Circle {
+        radius: f32,
+    },
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    Capsule {
+        radius: f32,
+        from: (f32, f32),
+        to: (f32, f32),
+    },
+}
+
+impl ColliderShape {
+    pub fn collider(&self) -> Collider {
+        match *self {
+            ColliderShape::Circle { radius } => Collider::circle(radius),
+            ColliderShape::Rectangle { width, height } => Collider::rectangle(width, height),
+            ColliderShape::Capsule { radius, from, to } => {
+                Collider::capsule_endpoints(radius, from.into(), to.into())
+            }
+        }
+    }
+}
+
+// What bursts out of a host this kind of enemy has infected.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Offspring {
+    pub archetype: String,
+    pub count: i32,
+    pub max: i32,
+}
+
+// Growing up and splitting in two, no host needed.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Fission {
+    pub seconds_to_mature: f32,
+    // It stops dividing once there are this many of its kind about.
+    pub max_population: usize,
+}
+
+// Everything that sets one kind of enemy apart from another.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct EnemyArchetype {
+    pub id: String,
+    pub sprite: String,
+    pub sprite_size: f32,
+    #[serde(default)]
+    pub flip_sprite: bool,
+    pub collider: ColliderShape,
+    pub speed: f32,
+    pub turn_speed: f32,
+    pub acceleration: f32,
+    pub drag: f32,
+    pub flow_susceptibility: f32,
+    pub behaviour: Hostile,
+    // A plain hit from a white blood cell takes away one.
+    #[serde(default = "one_hit")]
+    pub health: f32,
+    // Damage dealt by every bite, once a second, to the cells it is touching.
+    #[serde(default)]
+    pub contact_damage: f32,
+    #[serde(default)]
+    pub offspring: Option<Offspring>,
+    #[serde(default)]
+    pub fission: Option<Fission>,
+}
+
+fn one_hit() -> f32 {
+    1.
+}
+
+// Which archetype an enemy was made from.
+#[derive(Component, Clone, Debug)]
+pub struct EnemyType(pub String);
+
+#[derive(Resource)]
+pub struct EnemyRegistry {
+    archetypes: HashMap<String, EnemyArchetype>,
+}
+
+impl Default for EnemyRegistry {
+    fn default() -> Self {
+        let mut registry = EnemyRegistry {
+            archetypes: HashMap::new(),
+        };
+        for contents in BUILT_IN_ARCHETYPES {
+            let archetype =
+                ron::from_str(contents).expect("built-in enemy archetypes should be valid");
+            registry.insert(archetype);
+        }
+        registry
+    }
+}
+
+impl EnemyRegistry {
+    pub fn insert(&mut self, archetype: EnemyArchetype) {
+        self.archetypes.insert(archetype.id.clone(), archetype);
+    }
+
+    // Unknown ids, say from a typo in a file, fall back to the virus.
+    pub fn get(&self, id: &str) -> &EnemyArchetype {
+        self.archetypes.get(id).unwrap_or_else(|| {
+            warn!("Unknown enemy archetype {}", id);
+            &self.archetypes[VIRUS]
+        })
+    }
+}
+
+#[derive(Default)]
+struct ArchetypeLoader;
+
+impl AssetLoader for ArchetypeLoader {
+    type Asset = EnemyArchetype;
+    type Settings = ();
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        _load_context: &mut LoadContext<'_>,
+    ) -> Result<EnemyArchetype, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ron"]
+    }
+}
+
+// Holds on to the files so they stay loaded.
+#[derive(Resource)]
+struct ArchetypeHandles(Vec<Handle<EnemyArchetype>>);
+
+fn load_archetypes(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let handles = ARCHETYPE_FILES
+        .iter()
+        .map(|path| asset_server.load(*path))
+        .collect();
+    commands.insert_resource(ArchetypeHandles(handles));
+}
+
+// New enemies are made from the latest version of a file. The ones already
+// about only pick up how fast they move and turn, as their genome has it.
+fn update_registry(
+    mut events: EventReader<AssetEvent<EnemyArchetype>>,
+    handles: Res<ArchetypeHandles>,
+    archetypes: Res<Assets<EnemyArchetype>>,
+    mut registry: ResMut<EnemyRegistry>,
+    mut enemies: Query<(&EnemyType, &Genome, &mut Speed, &mut TurnSpeed)>,
+) {
+    for event in events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if !handles.0.iter().any(|handle| handle.id() == *id) {
+            continue;
+        }
+        let Some(archetype) = archetypes.get(*id) else {
+            continue;
+        };
+
+        if matches!(event, AssetEvent::Modified { .. }) {
+            info!("Reloaded enemy archetype {}", archetype.id);
+            for (enemy_type, genome, mut speed, mut turn_speed) in &mut enemies {
+                if enemy_type.0 == archetype.id {
+                    *speed = Speed::new(archetype.speed * genome.speed);
+                    turn_speed.0 = archetype.turn_speed * genome.turn_speed;
+                }
+            }
+        }
+        registry.insert(archetype.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_built_in_archetype_is_registered() {
+        let registry = EnemyRegistry::default();
+        assert_eq!(registry.archetypes.len(), BUILT_IN_ARCHETYPES.len());
+        assert_eq!(registry.get(VIRUS).id, VIRUS);
+        assert_eq!(registry.get(BACTERIA).id, BACTERIA);
+    }
+
+    #[test]
+    fn files_load_whichever_order_the_plugins_come_in() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ArchetypePlugin, AssetPlugin::default()));
+        app.finish();
+        app.update();
+
+        assert!(app.world().contains_resource::<Assets<EnemyArchetype>>());
+        assert!(app.world().contains_resource::<ArchetypeHandles>());
+    }
+}
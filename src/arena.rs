@@ -0,0 +1,145 @@
+use avian2d::prelude::*;
+use bevy::color::palettes::css::*;
+use bevy::prelude::*;
+use std::f32::consts::PI;
+
+use crate::schedule::GameState;
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Arena>();
+        // Gizmos are only available when rendering.
+        app.add_systems(
+            Update,
+            draw_vessel_wall
+                .run_if(in_state(GameState::Playing).and(resource_exists::<GizmoConfigStore>)),
+        );
+    }
+}
+
+// Round vessels are drawn and collided with as this many straight segments.
+const CIRCLE_SEGMENTS: usize = 64;
+const VESSEL_WALL_COLOR: Srgba = DARK_RED;
+
+// The outline of a blood vessel, centred on the origin where the player
+// starts. Polygons list their corners in order and must be able to see the
+// origin from every point of their outline.
+#[derive(Clone, Debug)]
+pub enum ArenaShape {
+    Circle { radius: f32 },
+    Polygon(Vec<Vec2>),
+}
+
+// The part of the level that bounds play. Everything stays inside the vessel
+// wall, bumping into it like into any other obstacle.
+#[derive(Resource, Clone, Debug)]
+pub struct Arena {
+    pub shape: ArenaShape,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Arena {
+            shape: ArenaShape::Circle { radius: 900. },
+        }
+    }
+}
+
+impl Arena {
+    pub fn outline(&self) -> Vec<Vec2> {
+        match &self.shape {
+            ArenaShape::Circle { radius } => (0..CIRCLE_SEGMENTS)
+                .map(|i| *radius * Vec2::from_angle(i as f32 * 2. * PI / CIRCLE_SEGMENTS as f32))
+                .collect(),
+            ArenaShape::Polygon(corners) => corners.clone(),
+        }
+    }
+
+    // How far from the origin the wall is, going in `direction`.
+    fn distance_to_wall(&self, direction: Vec2) -> f32 {
+        match &self.shape {
+            ArenaShape::Circle { radius } => *radius,
+            ArenaShape::Polygon(corners) => corners
+                .iter()
+                .zip(corners.iter().cycle().skip(1))
+                .filter_map(|(&a, &b)| ray_hits_segment(direction, a, b))
+                .fold(f32::MAX, f32::min),
+        }
+    }
+
+    // Pulls a point back toward the origin until it is at least `margin`
+    // inside the wall.
+    pub fn clamp(&self, point: Vec2, margin: f32) -> Vec2 {
+        let Some(direction) = point.try_normalize() else {
+            return point;
+        };
+        let limit = (self.distance_to_wall(direction) - margin).max(0.);
+        point.clamp_length_max(limit)
+    }
+}
+
+// The distance along a ray from the origin to where it crosses the segment
+// from `a` to `b`, if it does.
+fn ray_hits_segment(direction: Vec2, a: Vec2, b: Vec2) -> Option<f32> {
+    let edge = b - a;
+    let denominator = direction.perp_dot(edge);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let distance = a.perp_dot(edge) / denominator;
+    let along_edge = a.perp_dot(direction) / denominator;
+    (distance >= 0. && (0. ..=1.).contains(&along_edge)).then_some(distance)
+}
+
+#[derive(Component)]
+#[require(RigidBody = RigidBody::Static)]
+pub struct VesselWall;
+
+// Cells slide along the wall rather than sticking to it.
+pub fn vessel_wall(arena: &Arena) -> impl Bundle {
+    let mut outline = arena.outline();
+    outline.push(outline[0]);
+    (
+        VesselWall,
+        Transform::IDENTITY,
+        Collider::polyline(outline, None),
+        Friction::ZERO,
+        Restitution::new(0.3),
+    )
+}
+
+fn draw_vessel_wall(mut gizmos: Gizmos, arena: Res<Arena>) {
+    let mut outline = arena.outline();
+    outline.push(outline[0]);
+    gizmos.linestrip_2d(outline, VESSEL_WALL_COLOR);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamped_points_end_up_inside_the_wall() {
+        let circle = Arena::default();
+        assert_eq!(
+            circle.clamp(Vec2::new(1000., 0.), 100.),
+            Vec2::new(800., 0.)
+        );
+        assert_eq!(circle.clamp(Vec2::new(0., 50.), 100.), Vec2::new(0., 50.));
+
+        let square = Arena {
+            shape: ArenaShape::Polygon(vec![
+                Vec2::new(-500., -500.),
+                Vec2::new(500., -500.),
+                Vec2::new(500., 500.),
+                Vec2::new(-500., 500.),
+            ]),
+        };
+        let clamped = square.clamp(Vec2::new(2000., 1000.), 0.);
+        assert!((clamped - Vec2::new(500., 250.)).length() < 0.01);
+        let corner = square.clamp(Vec2::new(1000., 1000.), 0.);
+        assert!((corner - Vec2::new(500., 500.)).length() < 0.01);
+    }
+}
@@ -0,0 +1,13 @@
+use bevy::asset::AssetPath;
+use bevy::prelude::*;
+
+// Without the asset plugins, like in headless runs and tests, there is nothing
+// to load sprites with. Entities get an empty handle and are simply never drawn.
+pub fn load_image(
+    asset_server: Option<&AssetServer>,
+    path: impl Into<AssetPath<'static>>,
+) -> Handle<Image> {
+    asset_server
+        .map(|server| server.load(path))
+        .unwrap_or_default()
+}
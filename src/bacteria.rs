@@ -0,0 +1,161 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use crate::archetype::{EnemyRegistry, EnemyType};
+use crate::enemy::{create_enemy, Age, Hostile};
+use crate::genome::{GenePool, Genome};
+use crate::health::{DamageEvent, Health};
+use crate::rng::GameRng;
+use crate::schedule::InGameSet;
+
+pub struct BacteriaPlugin;
+
+impl Plugin for BacteriaPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            (grow_and_divide, bite_cells).in_set(InGameSet::EntityUpdates),
+        );
+    }
+}
+
+// Newly split bacteria start out this big and grow to full size.
+const NEWBORN_SCALE: f32 = 0.6;
+
+// Enemies that multiply by fission grow up over time and split in two, until
+// there are too many of their kind about.
+fn grow_and_divide(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    asset_server: Option<Res<AssetServer>>,
+    registry: Res<EnemyRegistry>,
+    mut gene_pool: ResMut<GenePool>,
+    mut enemies: Query<(Entity, &EnemyType, &Genome, &Age, &mut Transform)>,
+) {
+    let mut population: HashMap<String, usize> = HashMap::new();
+    for (_, enemy_type, _, _, _) in &enemies {
+        *population.entry(enemy_type.0.clone()).or_default() += 1;
+    }
+
+    for (entity, enemy_type, genome, age, mut transform) in &mut enemies {
+        let archetype = registry.get(&enemy_type.0);
+        let Some(fission) = &archetype.fission else {
+            continue;
+        };
+        let grown = (age.0 / fission.seconds_to_mature).min(1.);
+        transform.scale = Vec3::splat(NEWBORN_SCALE + (1. - NEWBORN_SCALE) * grown);
+
+        let count = population.entry(enemy_type.0.clone()).or_default();
+        if grown < 1. || *count >= fission.max_population {
+            continue;
+        }
+        *count += 1;
+        gene_pool.record(&archetype.id, *genome);
+
+        // The two halves push off from each other.
+        let apart = Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
+        let position = transform.translation.xy();
+        for direction in [apart, -apart] {
+            let genome = genome.mutate(&mut **rng);
+            commands.spawn(
+                create_enemy(
+                    &registry,
+                    asset_server.as_deref(),
+                    &archetype.id,
+                    direction,
+                    position + direction * archetype.sprite_size / 4.,
+                )
+                .with_genome(genome),
+            );
+        }
+        commands.entity(entity).despawn();
+    }
+}
+
+const BITE_SECS: f32 = 1.;
+
+// Enemies with a bite take one out of every cell they are touching, hosts
+// and white blood cells alike, every `BITE_SECS`.
+fn bite_cells(
+    time: Res<Time>,
+    registry: Res<EnemyRegistry>,
+    mut damage: EventWriter<DamageEvent>,
+    enemies: Query<(Entity, &EnemyType, &Age, &CollidingEntities)>,
+    cells: Query<(), (With<Health>, Without<Hostile>)>,
+) {
+    for (enemy, enemy_type, age, touching) in &enemies {
+        let amount = registry.get(&enemy_type.0).contact_damage;
+        let bites = (age.0 / BITE_SECS).floor() - ((age.0 - time.delta_secs()) / BITE_SECS).floor();
+        if amount <= 0. || bites < 1. {
+            continue;
+        }
+        for &entity in touching.iter().filter(|&&entity| cells.contains(entity)) {
+            damage.write(DamageEvent {
+                target: entity,
+                amount,
+                source: Some(enemy),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetype::BACTERIA;
+    use crate::host::HOST_HEALTH;
+    use crate::test_harness::{
+        count, run_frames, spawn_enemy, spawn_host, spawn_white_blood_cell, test_app,
+    };
+
+    #[test]
+    fn bacteria_split_in_two_when_grown() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let bacterium = spawn_enemy(&mut app, BACTERIA, Vec2::ZERO, Vec2::X);
+        app.world_mut().get_mut::<Age>(bacterium).unwrap().0 = 7.9;
+        run_frames(&mut app, 10);
+
+        assert!(app.world().get_entity(bacterium).is_err());
+        assert_eq!(count::<With<Hostile>>(&mut app), 2);
+    }
+
+    #[test]
+    fn bacteria_bite_hosts_they_touch() {
+        let mut app = test_app();
+        let host = spawn_host(&mut app, Vec2::ZERO);
+        let bacterium = spawn_enemy(&mut app, BACTERIA, Vec2::new(30., 0.), Vec2::NEG_X);
+        run_frames(&mut app, 80);
+
+        // Still free, and it has taken a bite.
+        assert!(app.world().get::<ChildOf>(bacterium).is_none());
+        let health = app.world().get::<Health>(host).unwrap().current;
+        assert_eq!(health, HOST_HEALTH - 1.);
+
+        app.world_mut().get_mut::<Health>(host).unwrap().current = 0.;
+        run_frames(&mut app, 1);
+        assert!(app.world().get_entity(host).is_err());
+    }
+
+    #[test]
+    fn bacteria_take_several_hits() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let bacterium = spawn_enemy(&mut app, BACTERIA, Vec2::ZERO, Vec2::X);
+
+        // Every white blood cell that bumps into it lands one hit.
+        for hits in [2, 1] {
+            let cell = spawn_white_blood_cell(&mut app, Vec2::new(-20., 0.));
+            run_frames(&mut app, 3);
+            let health = app.world().get::<Health>(bacterium).unwrap().current;
+            assert_eq!(health, hits as f32);
+            app.world_mut().entity_mut(cell).despawn();
+        }
+        spawn_white_blood_cell(&mut app, Vec2::new(-20., 0.));
+        run_frames(&mut app, 3);
+        assert!(app.world().get_entity(bacterium).is_err());
+    }
+}
@@ -0,0 +1,430 @@
+use bevy::input::gamepad::GamepadConnectionEvent;
+use bevy::prelude::*;
+use bevy::reflect::{DynamicEnum, DynamicVariant, Enum, TypeInfo, Typed, VariantInfo};
+use bevy::window::PrimaryWindow;
+use bevy_enhanced_input::prelude::*;
+
+use crate::player::Player;
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(EnhancedInputPlugin)
+            .add_input_context::<PlayerControls>()
+            .add_observer(bind_player_controls)
+            .init_resource::<InputDevice>()
+            .init_resource::<KeyBindings>()
+            .init_resource::<TouchControls>()
+            .init_resource::<MouseSteering>()
+            .init_resource::<MouseCursor>()
+            .init_resource::<PlayerInput>()
+            .add_systems(Update, (log_gamepad_connections, detect_input_device))
+            .add_systems(
+                RunFixedMainLoop,
+                (track_mouse_cursor, gather_player_input)
+                    .chain()
+                    .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+            )
+            .add_systems(FixedLast, clear_released_input);
+    }
+}
+
+// Everything the player cell can do. Lives on the player entity as
+// `Actions<PlayerControls>`.
+#[derive(InputContext)]
+pub struct PlayerControls;
+
+#[derive(Debug, InputAction)]
+#[input_action(output = Vec2)]
+pub struct Move;
+
+// Charges while held and fires on release.
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub struct Boost;
+
+// Charges while held, spawning a clone every time the bar fills up.
+#[derive(Debug, InputAction)]
+#[input_action(output = bool)]
+pub struct Replicate;
+
+fn bind_player_controls(
+    trigger: Trigger<Binding<PlayerControls>>,
+    keys: Res<KeyBindings>,
+    steering: Res<MouseSteering>,
+    mut players: Query<&mut Actions<PlayerControls>>,
+) {
+    let Ok(mut actions) = players.get_mut(trigger.target()) else {
+        return;
+    };
+
+    // The radial dead zone also keeps diagonals on the keyboard from being
+    // faster, while a half tilted stick still means half speed.
+    actions
+        .bind::<Move>()
+        .to((
+            Cardinal {
+                north: keys.get(RebindableKey::MoveUp),
+                east: keys.get(RebindableKey::MoveRight),
+                south: keys.get(RebindableKey::MoveDown),
+                west: keys.get(RebindableKey::MoveLeft),
+            },
+            Axial::left_stick(),
+        ))
+        .with_modifiers(DeadZone::default());
+    actions.bind::<Boost>().to((
+        keys.get(RebindableKey::Boost),
+        GamepadButton::South,
+        GamepadButton::RightTrigger2,
+    ));
+    if steering.0 {
+        actions.bind::<Boost>().to(MouseButton::Left);
+    }
+    actions.bind::<Replicate>().to((
+        keys.get(RebindableKey::Replicate),
+        GamepadButton::LeftTrigger,
+    ));
+}
+
+// The keyboard controls the player can change. Gamepad buttons stay fixed.
+// Each one's value is its place in `ALL` and in `KeyBindings`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(usize)]
+pub enum RebindableKey {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Boost,
+    Replicate,
+}
+
+impl RebindableKey {
+    pub const ALL: [RebindableKey; 6] = [
+        RebindableKey::MoveUp,
+        RebindableKey::MoveDown,
+        RebindableKey::MoveLeft,
+        RebindableKey::MoveRight,
+        RebindableKey::Boost,
+        RebindableKey::Replicate,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RebindableKey::MoveUp => "Move up",
+            RebindableKey::MoveDown => "Move down",
+            RebindableKey::MoveLeft => "Move left",
+            RebindableKey::MoveRight => "Move right",
+            RebindableKey::Boost => "Boost",
+            RebindableKey::Replicate => "Self-replicate",
+        }
+    }
+
+    // Name used in the settings file.
+    fn setting_name(self) -> &'static str {
+        match self {
+            RebindableKey::MoveUp => "move_up",
+            RebindableKey::MoveDown => "move_down",
+            RebindableKey::MoveLeft => "move_left",
+            RebindableKey::MoveRight => "move_right",
+            RebindableKey::Boost => "boost",
+            RebindableKey::Replicate => "replicate",
+        }
+    }
+}
+
+// Keyboard bindings in the same order as `RebindableKey::ALL`. Changing them
+// only takes effect on the player once `RebuildBindings` is triggered.
+#[derive(Resource, Clone, PartialEq, Eq, Debug)]
+pub struct KeyBindings([KeyCode; 6]);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings([
+            KeyCode::ArrowUp,
+            KeyCode::ArrowDown,
+            KeyCode::ArrowLeft,
+            KeyCode::ArrowRight,
+            KeyCode::Space,
+            KeyCode::ShiftLeft,
+        ])
+    }
+}
+
+impl KeyBindings {
+    pub fn get(&self, action: RebindableKey) -> KeyCode {
+        self.0[action as usize]
+    }
+
+    pub fn set(&mut self, action: RebindableKey, key: KeyCode) {
+        self.0[action as usize] = key;
+    }
+
+    // The action already using `key`, if any.
+    pub fn action_for(&self, key: KeyCode) -> Option<RebindableKey> {
+        RebindableKey::ALL
+            .into_iter()
+            .find(|&action| self.get(action) == key)
+    }
+
+    // One "action KeyCode" line per binding. Unknown or missing lines keep
+    // their default so an old settings file still loads.
+    pub fn parse(contents: &str) -> Self {
+        let mut bindings = KeyBindings::default();
+        for line in contents.lines() {
+            let mut fields = line.split_whitespace();
+            let (Some(name), Some(key_name)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let action = RebindableKey::ALL
+                .into_iter()
+                .find(|action| action.setting_name() == name);
+            if let (Some(action), Some(key)) = (action, parse_key(key_name)) {
+                bindings.set(action, key);
+            }
+        }
+        bindings
+    }
+
+    pub fn serialize(&self) -> String {
+        RebindableKey::ALL
+            .iter()
+            .map(|&action| {
+                format!(
+                    "{} {}\n",
+                    action.setting_name(),
+                    self.get(action).variant_name()
+                )
+            })
+            .collect()
+    }
+}
+
+fn parse_key(name: &str) -> Option<KeyCode> {
+    // Only the plain named keys, reflection panics on variants that don't exist.
+    let TypeInfo::Enum(info) = KeyCode::type_info() else {
+        return None;
+    };
+    let Some(VariantInfo::Unit(_)) = info.variant(name) else {
+        return None;
+    };
+    KeyCode::from_reflect(&DynamicEnum::new(name, DynamicVariant::Unit))
+}
+
+// Short name for prompts, e.g. "W" rather than "KeyW".
+pub fn key_label(key: KeyCode) -> String {
+    let name = key.variant_name();
+    name.strip_prefix("Key")
+        .or_else(|| name.strip_prefix("Digit"))
+        .unwrap_or(name)
+        .to_string()
+}
+
+// Optional scheme where the player cell follows the cursor and a held left
+// click charges a dash toward wherever the button is released. Changing it only
+// takes effect on the player once `RebuildBindings` is triggered.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MouseSteering(pub bool);
+
+const MOUSE_STEERING_SETTING: &str = "mouse_steering";
+
+impl MouseSteering {
+    pub fn parse(contents: &str) -> Self {
+        let enabled = contents.lines().any(|line| {
+            let mut fields = line.split_whitespace();
+            fields.next() == Some(MOUSE_STEERING_SETTING) && fields.next() == Some("on")
+        });
+        MouseSteering(enabled)
+    }
+
+    pub fn serialize(&self) -> String {
+        let value = if self.0 { "on" } else { "off" };
+        format!("{MOUSE_STEERING_SETTING} {value}\n")
+    }
+}
+
+// Where the cursor points in the world, if it is over the window.
+#[derive(Resource, Default)]
+pub struct MouseCursor(pub Option<Vec2>);
+
+fn track_mouse_cursor(
+    window: Single<&Window, With<PrimaryWindow>>,
+    camera: Single<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut cursor: ResMut<MouseCursor>,
+) {
+    let (camera, camera_transform) = *camera;
+    cursor.0 = window
+        .cursor_position()
+        .and_then(|position| camera.viewport_to_world_2d(camera_transform, position).ok());
+}
+
+// The device the player last touched, so prompts can name the right buttons.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq)]
+pub enum InputDevice {
+    #[default]
+    Keyboard,
+    Gamepad,
+    Touch,
+}
+
+const STICK_ACTIVITY_THRESHOLD: f32 = 0.5;
+
+fn detect_input_device(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    touches: Res<Touches>,
+    gamepads: Query<&Gamepad>,
+    mut device: ResMut<InputDevice>,
+) {
+    let gamepad_used = gamepads.iter().any(|gamepad| {
+        gamepad.get_just_pressed().next().is_some()
+            || gamepad.left_stick().length() > STICK_ACTIVITY_THRESHOLD
+    });
+
+    let new_device = if gamepad_used {
+        InputDevice::Gamepad
+    } else if touches.any_just_pressed() {
+        InputDevice::Touch
+    } else if keyboard.get_just_pressed().next().is_some()
+        || (*device == InputDevice::Gamepad && gamepads.is_empty())
+    {
+        // Also fall back to the keyboard when the last pad is unplugged.
+        InputDevice::Keyboard
+    } else {
+        *device
+    };
+    device.set_if_neq(new_device);
+}
+
+fn log_gamepad_connections(mut events: EventReader<GamepadConnectionEvent>) {
+    for event in events.read() {
+        if event.connected() {
+            info!("Gamepad {} connected", event.gamepad);
+        } else if event.disconnected() {
+            info!("Gamepad {} disconnected", event.gamepad);
+        }
+    }
+}
+
+// Everything the player asked for, whichever device it came from. Gameplay
+// reads this rather than the devices so a replay can stand in for them.
+// Releases are kept until a fixed step has seen them, as a frame may run none.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Debug)]
+pub struct PlayerInput {
+    // Direction and strength to move in, up to length 1.
+    pub movement: Vec2,
+    pub boost_held: bool,
+    pub boost_released: bool,
+    pub replicate_held: bool,
+    pub replicate_released: bool,
+    // Where a released boost dashes to when aiming with the mouse.
+    pub dash_direction: Option<Vec2>,
+}
+
+// Within this distance of the cursor the player slows down instead of
+// overshooting and circling around it.
+const MOUSE_SLOWDOWN_DISTANCE: f32 = 100.;
+
+pub fn gather_player_input(
+    touch: Res<TouchControls>,
+    steering: Res<MouseSteering>,
+    cursor: Res<MouseCursor>,
+    players: Query<(&Actions<PlayerControls>, &Transform), With<Player>>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let Ok((actions, transform)) = players.single() else {
+        *input = PlayerInput::default();
+        return;
+    };
+    let (Ok(movement), Ok(boost), Ok(replicate)) = (
+        actions.value::<Move>(),
+        actions.get::<Boost>(),
+        actions.get::<Replicate>(),
+    ) else {
+        *input = PlayerInput::default();
+        return;
+    };
+
+    let aim = match (steering.0, cursor.0) {
+        (true, Some(target)) => Some(target - transform.translation.xy()),
+        _ => None,
+    };
+    let boost_released = was_released(boost) || touch.boost.released;
+    let dash_direction = match aim {
+        Some(offset) if boost_released => Some(offset.normalize_or_zero()),
+        _ => input.dash_direction,
+    };
+
+    *input = PlayerInput {
+        movement: match aim {
+            Some(offset) => (offset / MOUSE_SLOWDOWN_DISTANCE).clamp_length_max(1.),
+            None => (movement.as_axis2d() + touch.movement).clamp_length_max(1.),
+        },
+        boost_held: is_held(boost) || touch.boost.held,
+        boost_released: input.boost_released || boost_released,
+        replicate_held: is_held(replicate) || touch.replicate.held,
+        replicate_released: input.replicate_released
+            || was_released(replicate)
+            || touch.replicate.released,
+        dash_direction,
+    };
+}
+
+fn clear_released_input(mut input: ResMut<PlayerInput>) {
+    input.boost_released = false;
+    input.replicate_released = false;
+    input.dash_direction = None;
+}
+
+// What the on-screen controls of the touch layout are doing this frame. Merged
+// into `PlayerInput`, since touches can't be bound to the player's `Actions`.
+#[derive(Resource, Default)]
+pub struct TouchControls {
+    // Joystick tilt, up to length 1.
+    pub movement: Vec2,
+    pub boost: TouchButtonState,
+    pub replicate: TouchButtonState,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct TouchButtonState {
+    pub held: bool,
+    // Only true on the frame the finger is lifted.
+    pub released: bool,
+}
+
+fn is_held(action: &Action) -> bool {
+    action.state() == ActionState::Fired
+}
+
+fn was_released(action: &Action) -> bool {
+    action.events().contains(ActionEvents::COMPLETED)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_bindings_survive_a_save_and_load() {
+        let mut bindings = KeyBindings::default();
+        bindings.set(RebindableKey::MoveUp, KeyCode::KeyW);
+        bindings.set(RebindableKey::Boost, KeyCode::KeyJ);
+
+        let loaded = KeyBindings::parse(&bindings.serialize());
+        assert_eq!(loaded, bindings);
+        assert_eq!(loaded.action_for(KeyCode::KeyJ), Some(RebindableKey::Boost));
+        assert_eq!(key_label(loaded.get(RebindableKey::MoveUp)), "W");
+
+        // Garbage lines are skipped and leave the defaults in place.
+        let loaded = KeyBindings::parse("boost NotAKey\nsomething else\n");
+        assert_eq!(loaded, KeyBindings::default());
+    }
+
+    #[test]
+    fn every_key_is_bound_in_its_own_slot() {
+        for (index, action) in RebindableKey::ALL.into_iter().enumerate() {
+            assert_eq!(action as usize, index);
+        }
+    }
+}
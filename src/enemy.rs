@@ -1,15 +1,61 @@
 use avian2d::prelude::*;
+use bevy::color::palettes::css::{BLUE, RED};
 use bevy::prelude::*;
 use rand::seq::IndexedRandom;
+use serde::Deserialize;
 
+use crate::archetype::{EnemyRegistry, EnemyType};
+use crate::assets::load_image;
+use crate::genome::Genome;
+use crate::health::Health;
 use crate::host::Host;
-use crate::movement::{Speed, TurnSpeed, Velocity};
+use crate::movement::{FlowSusceptibility, GameLayer, Inertia, Speed, TurnSpeed, Velocity};
+use crate::navigation::NavPath;
+use crate::rng::GameRng;
 use crate::schedule::InGameSet;
+use crate::spatial::{FreeViruses, Hosts, SpatialIndex};
 
-#[derive(Component)]
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+#[require(CollisionLayers = CollisionLayers::new(
+    GameLayer::Virus,
+    [GameLayer::Default, GameLayer::Cell]
+))]
 pub enum Hostile {
     // Should only attack once and then die.
     InfectThenDie,
+    // Wears hosts down by touching them and multiplies on its own.
+    Divide,
+}
+
+// How much an enemy heeds each pull while steering. Seeking heads for the
+// target, separation keeps it from stacking on top of its neighbours,
+// alignment has it go their way and cohesion keeps it close to them.
+pub struct SwarmWeights {
+    pub seek: f32,
+    pub separation: f32,
+    pub alignment: f32,
+    pub cohesion: f32,
+}
+
+impl Hostile {
+    pub fn swarm_weights(&self) -> SwarmWeights {
+        match self {
+            // A loose cloud that fans out around the host it goes for.
+            Hostile::InfectThenDie => SwarmWeights {
+                seek: 1.0,
+                separation: 1.5,
+                alignment: 0.4,
+                cohesion: 0.3,
+            },
+            // Colonies stick together and drift along as one.
+            Hostile::Divide => SwarmWeights {
+                seek: 1.0,
+                separation: 1.0,
+                alignment: 0.6,
+                cohesion: 0.8,
+            },
+        }
+    }
 }
 
 #[derive(Component)]
@@ -18,52 +64,76 @@ pub struct Targeting(pub Entity);
 #[derive(Component)]
 pub struct VirusAttached;
 
+// Seconds since it spawned.
+#[derive(Component, Default)]
+pub struct Age(pub f32);
+
 #[derive(Bundle)]
-pub struct VirusBundle {
+pub struct EnemyBundle {
     pub sprite: Sprite,
     pub transform: Transform,
     pub collider: Collider,
     pub velocity: Velocity,
     pub speed: Speed,
     pub turn_speed: TurnSpeed,
+    pub inertia: Inertia,
+    pub flow_susceptibility: FlowSusceptibility,
     pub enemy_class: Hostile,
+    pub enemy_type: EnemyType,
+    pub health: Health,
+    pub age: Age,
+    pub genome: Genome,
     pub colliding_entities: CollidingEntities,
 }
 
-const VIRUS_SPRITE_FILEPATH: &'static str = "virus.png";
-pub const VIRUS_HITBOX: (f32, f32) = (11.0, 8.0);
-pub const VIRUS_SPRITE_SIZE: Vec2 = Vec2::splat(20.);
+impl EnemyBundle {
+    // Gives a freshly created enemy the traits it inherited, tinting it redder
+    // the faster it is and bluer the slower.
+    pub fn with_genome(mut self, genome: Genome) -> Self {
+        self.velocity.value *= genome.speed;
+        self.speed = Speed::new(self.speed.default * genome.speed);
+        self.turn_speed.0 *= genome.turn_speed;
+        let tint = if genome.speed > 1. { RED } else { BLUE };
+        self.sprite.color =
+            Color::WHITE.mix(&tint.into(), ((genome.speed - 1.).abs() * 2.).min(1.));
+        self.genome = genome;
+        self
+    }
+}
 
-pub fn create_virus(
-    asset_server: &Res<AssetServer>,
+// An enemy of the archetype `id`, setting off in `direction`.
+pub fn create_enemy(
+    registry: &EnemyRegistry,
+    asset_server: Option<&AssetServer>,
+    id: &str,
     direction: Vec2,
     position: Vec2,
-) -> VirusBundle {
-    VirusBundle {
+) -> EnemyBundle {
+    let archetype = registry.get(id);
+    EnemyBundle {
         sprite: Sprite {
-            image: asset_server.load(VIRUS_SPRITE_FILEPATH),
-            custom_size: Some(VIRUS_SPRITE_SIZE),
-            flip_y: true,
+            image: load_image(asset_server, archetype.sprite.clone()),
+            custom_size: Some(Vec2::splat(archetype.sprite_size)),
+            flip_y: archetype.flip_sprite,
             ..default()
         },
         transform: Transform {
             translation: position.extend(0.),
             ..default()
         },
-        collider: Collider::capsule_endpoints(
-            VIRUS_HITBOX.0 / 2.0,
-            Vec2::new(0.0, 0.0),
-            Vec2::new(0.0, -3.0),
-        ),
+        collider: archetype.collider.collider(),
         velocity: Velocity {
-            value: direction.extend(0.) * VIRUS_SPEED,
-        },
-        speed: Speed {
-            current: VIRUS_SPEED,
-            default: VIRUS_SPEED,
+            value: direction.extend(0.) * archetype.speed,
         },
-        turn_speed: TurnSpeed(1.0),
-        enemy_class: Hostile::InfectThenDie,
+        speed: Speed::new(archetype.speed),
+        turn_speed: TurnSpeed(archetype.turn_speed),
+        inertia: Inertia::new(archetype.acceleration, archetype.drag),
+        flow_susceptibility: FlowSusceptibility(archetype.flow_susceptibility),
+        enemy_class: archetype.behaviour,
+        enemy_type: EnemyType(archetype.id.clone()),
+        health: Health::new(archetype.health),
+        age: Age::default(),
+        genome: Genome::default(),
         colliding_entities: CollidingEntities::default(),
     }
 }
@@ -73,20 +143,27 @@ pub struct EnemyPlugin;
 impl Plugin for EnemyPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
-            Update,
+            FixedUpdate,
             (set_target, unset_nonexisting_target).in_set(InGameSet::EntityUpdates),
         );
-        app.add_systems(Update, set_velocity.in_set(InGameSet::EntityUpdates));
+        app.add_systems(
+            FixedUpdate,
+            (set_velocity, grow_older).in_set(InGameSet::EntityUpdates),
+        );
     }
 }
 
+// Viruses go for one of the few hosts closest to where they are.
+const HOST_CANDIDATES: usize = 3;
+
 fn set_target(
     mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    hosts: Res<SpatialIndex<Hosts>>,
     mut viruses: Query<
-        Entity,
+        (Entity, &Transform),
         (
             With<Velocity>,
-            With<Transform>,
             With<TurnSpeed>,
             With<Hostile>,
             Without<Host>,
@@ -94,11 +171,10 @@ fn set_target(
             Without<VirusAttached>,
         ),
     >,
-    targets: Query<Entity, (With<Transform>, With<Host>, Without<Hostile>)>,
 ) {
-    let targets_list: Vec<Entity> = targets.iter().collect();
-    for virus in viruses.iter_mut() {
-        if let Some(&random_target) = targets_list.choose(&mut rand::rng()) {
+    for (virus, transform) in viruses.iter_mut() {
+        let candidates = hosts.nearest(transform.translation.xy(), HOST_CANDIDATES);
+        if let Some(&(random_target, _)) = candidates.choose(&mut **rng) {
             commands.entity(virus).insert(Targeting(random_target));
         }
     }
@@ -116,33 +192,135 @@ fn unset_nonexisting_target(
     }
 }
 
-pub const VIRUS_SPEED: f32 = 20.0;
+fn grow_older(time: Res<Time>, mut ages: Query<&mut Age>) {
+    for mut age in &mut ages {
+        age.0 += time.delta_secs();
+    }
+}
+
 const FAST_ROTATE_DISTANCE: f32 = 20.0;
+// How far a virus looks around for the rest of its swarm.
+const SWARM_RADIUS: f32 = 60.0;
+
+// Where a virus at `position` would like to head, all pulls considered.
+fn swarm_direction(
+    entity: Entity,
+    position: Vec2,
+    to_target: Vec2,
+    weights: &SwarmWeights,
+    swarm: &SpatialIndex<FreeViruses>,
+    motion: &Query<&LinearVelocity>,
+) -> Vec2 {
+    let mut separation = Vec2::ZERO;
+    let mut heading = Vec2::ZERO;
+    let mut center = Vec2::ZERO;
+    let mut neighbours = 0;
+    for (neighbour, neighbour_position) in swarm.within_radius(position, SWARM_RADIUS) {
+        if neighbour == entity {
+            continue;
+        }
+        let away = position - neighbour_position;
+        // The closer a neighbour, the harder the push away from it.
+        separation += away.normalize_or_zero() * (1.0 - away.length() / SWARM_RADIUS);
+        if let Ok(velocity) = motion.get(neighbour) {
+            heading += velocity.0;
+        }
+        center += neighbour_position;
+        neighbours += 1;
+    }
+
+    let mut direction = to_target.normalize_or_zero() * weights.seek;
+    if neighbours > 0 {
+        direction += separation * weights.separation
+            + heading.normalize_or_zero() * weights.alignment
+            + (center / neighbours as f32 - position).normalize_or_zero() * weights.cohesion;
+    }
+    direction.normalize_or(to_target.normalize_or_zero())
+}
 
 fn set_velocity(
     time: Res<Time>,
-    mut viruses: Query<(&mut Velocity, &Transform, &Targeting, &TurnSpeed)>,
+    swarm: Res<SpatialIndex<FreeViruses>>,
+    mut viruses: Query<(
+        Entity,
+        &mut Velocity,
+        &Transform,
+        &Targeting,
+        &Speed,
+        &TurnSpeed,
+        &Hostile,
+    )>,
+    paths: Query<&NavPath>,
     targets: Query<&Transform>,
+    motion: Query<&LinearVelocity>,
 ) {
-    // go towards the target
-    for (mut velocity, seeker_transform, targeting, turn_speed) in viruses.iter_mut() {
+    // go towards the target, together with the rest of the swarm and around
+    // whatever is in the way
+    for (entity, mut velocity, seeker_transform, targeting, speed, turn_speed, hostile) in
+        viruses.iter_mut()
+    {
         let Ok(target) = targets.get(targeting.0) else {
             continue;
         };
 
-        let to_target = target.translation.xy() - seeker_transform.translation.xy();
+        let position = seeker_transform.translation.xy();
+        let to_target = target.translation.xy() - position;
         if to_target.length() < 0.01 {
             velocity.value = Vec3::ZERO;
-        } else if to_target.length() < FAST_ROTATE_DISTANCE || velocity.value.length() < 0.01 {
-            velocity.value = to_target.extend(0.0).normalize() * VIRUS_SPEED;
+        } else if to_target.length() < FAST_ROTATE_DISTANCE {
+            velocity.value = to_target.extend(0.0).normalize() * speed.current;
         } else {
+            let waypoint = paths
+                .get(entity)
+                .ok()
+                .and_then(NavPath::next_waypoint)
+                .unwrap_or(target.translation.xy());
+            let weights = hostile.swarm_weights();
+            let wanted = swarm_direction(
+                entity,
+                position,
+                waypoint - position,
+                &weights,
+                &swarm,
+                &motion,
+            );
+            if velocity.value.length() < 0.01 {
+                velocity.value = wanted.extend(0.) * speed.current;
+                continue;
+            }
             let new_direction = velocity
                 .value
                 .xy()
-                .rotate_towards(to_target, turn_speed.0 * time.delta_secs());
+                .rotate_towards(wanted, turn_speed.0 * time.delta_secs());
 
-            velocity.value = new_direction.normalize_or_zero().extend(0.) * VIRUS_SPEED;
+            velocity.value = new_direction.normalize_or_zero().extend(0.) * speed.current;
         }
     }
     //
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{run_frames, spawn_host, spawn_virus, test_app};
+
+    #[test]
+    fn stacked_viruses_spread_out_on_the_way() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(600., 0.));
+        let first = spawn_virus(&mut app, Vec2::ZERO, Vec2::X);
+        let second = spawn_virus(&mut app, Vec2::new(0., 1.), Vec2::X);
+        run_frames(&mut app, 120);
+
+        let position = |virus| {
+            app.world()
+                .get::<Transform>(virus)
+                .unwrap()
+                .translation
+                .xy()
+        };
+        assert!(position(first).distance(position(second)) > 10.);
+        // Still on their way to the host.
+        assert!(position(first).x > 20.);
+    }
+}
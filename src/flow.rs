@@ -0,0 +1,132 @@
+use bevy::prelude::*;
+
+use crate::arena::Arena;
+use crate::host::Host;
+use crate::schedule::InGameSet;
+
+pub struct FlowPlugin;
+
+impl Plugin for FlowPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FlowField>();
+        app.add_systems(FixedUpdate, update_flow_field.in_set(InGameSet::UserInput));
+    }
+}
+
+// The bloodstream runs left to right through the vessel.
+const STREAM: Vec2 = Vec2::new(12., 0.);
+// Wall cells stir up eddies around them, strongest half way out.
+const SWIRL_RADIUS: f32 = 120.;
+const SWIRL_SPEED: f32 = 25.;
+const FLOW_CELL_SIZE: f32 = 40.;
+
+// The current at every point of the arena, sampled on a grid. Still water
+// until there is an arena with wall cells in it.
+#[derive(Resource, Default)]
+pub struct FlowField {
+    origin: Vec2,
+    columns: usize,
+    rows: usize,
+    flow: Vec<Vec2>,
+}
+
+impl FlowField {
+    pub fn new(arena: &Arena, hosts: &[Vec2]) -> Self {
+        let outline = arena.outline();
+        let min = outline.iter().copied().fold(Vec2::MAX, Vec2::min);
+        let max = outline.iter().copied().fold(Vec2::MIN, Vec2::max);
+        let size = ((max - min) / FLOW_CELL_SIZE).ceil().as_uvec2() + 1;
+        let (columns, rows) = (size.x as usize, size.y as usize);
+
+        let mut flow = Vec::with_capacity(columns * rows);
+        for row in 0..rows {
+            for column in 0..columns {
+                let point = min + Vec2::new(column as f32, row as f32) * FLOW_CELL_SIZE;
+                flow.push(current_at(point, hosts));
+            }
+        }
+        FlowField {
+            origin: min,
+            columns,
+            rows,
+            flow,
+        }
+    }
+
+    // Blends the four grid points around `point`. Beyond the grid the
+    // current of its edge carries on.
+    pub fn sample(&self, point: Vec2) -> Vec2 {
+        if self.flow.is_empty() {
+            return Vec2::ZERO;
+        }
+        let max = Vec2::new(self.columns as f32 - 1., self.rows as f32 - 1.);
+        let grid = ((point - self.origin) / FLOW_CELL_SIZE).clamp(Vec2::ZERO, max);
+        let corner = grid.floor().min(max - 1.).max(Vec2::ZERO);
+        let t = grid - corner;
+        let (column, row) = (corner.x as usize, corner.y as usize);
+
+        let at = |column: usize, row: usize| {
+            self.flow[row.min(self.rows - 1) * self.columns + column.min(self.columns - 1)]
+        };
+        let bottom = at(column, row).lerp(at(column + 1, row), t.x);
+        let top = at(column, row + 1).lerp(at(column + 1, row + 1), t.x);
+        bottom.lerp(top, t.y)
+    }
+}
+
+fn current_at(point: Vec2, hosts: &[Vec2]) -> Vec2 {
+    hosts.iter().fold(STREAM, |current, &host| {
+        let offset = point - host;
+        let distance = offset.length() / SWIRL_RADIUS;
+        if distance >= 1. || distance == 0. {
+            return current;
+        }
+        // Eddies go along with the stream on the side facing the middle of
+        // the vessel, and against it on the side facing its wall.
+        let turn = if host.y < 0. { -1. } else { 1. };
+        let strength = 4. * distance * (1. - distance) * SWIRL_SPEED;
+        current + turn * offset.normalize().perp() * strength
+    })
+}
+
+// Rebuilt when wall cells come and go, as the eddies around them do too.
+fn update_flow_field(
+    mut flow_field: ResMut<FlowField>,
+    arena: Res<Arena>,
+    hosts: Query<&Transform, With<Host>>,
+    added: Query<(), Added<Host>>,
+    mut removed: RemovedComponents<Host>,
+) {
+    let removed = removed.read().count() > 0;
+    if added.is_empty() && !removed && !arena.is_changed() {
+        return;
+    }
+    let hosts: Vec<Vec2> = hosts
+        .iter()
+        .map(|transform| transform.translation.xy())
+        .collect();
+    *flow_field = FlowField::new(&arena, &hosts);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn current_streams_past_and_swirls_around_wall_cells() {
+        let arena = Arena::default();
+        let still = FlowField::default();
+        assert_eq!(still.sample(Vec2::ZERO), Vec2::ZERO);
+
+        let host = Vec2::new(0., -200.);
+        let flow_field = FlowField::new(&arena, &[host]);
+        // Far from the wall cell only the stream is left.
+        assert!((flow_field.sample(Vec2::new(-500., 300.)) - STREAM).length() < 0.01);
+        // Toward the middle the eddy speeds the stream up, toward the wall it
+        // runs back upstream.
+        let inside = flow_field.sample(host + Vec2::new(0., SWIRL_RADIUS / 2.));
+        let outside = flow_field.sample(host - Vec2::new(0., SWIRL_RADIUS / 2.));
+        assert!(inside.x > STREAM.x + 10.);
+        assert!(outside.x < 0.);
+    }
+}
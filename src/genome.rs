@@ -0,0 +1,220 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+use std::collections::HashMap;
+
+use crate::archetype::EnemyType;
+use crate::level::EnemySpawner;
+
+pub struct GenomePlugin;
+
+impl Plugin for GenomePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GenePool>();
+        app.init_resource::<Evolution>();
+        app.add_observer(record_genome);
+    }
+}
+
+// How far each trait can drift, either way, from one generation to the next.
+const MUTATION: f32 = 0.1;
+// Traits never drift further than this from what their archetype says.
+const MIN_TRAIT: f32 = 0.25;
+const MAX_TRAIT: f32 = 4.0;
+// Only the most recent successes are remembered.
+const GENE_POOL_SIZE: usize = 64;
+
+// Heritable traits, as multiples of what the enemy's archetype says.
+// Infectiousness is how quickly an infected host bursts, burst size how many
+// offspring come out of it.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Genome {
+    pub speed: f32,
+    pub turn_speed: f32,
+    pub infectiousness: f32,
+    pub burst_size: f32,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Genome {
+            speed: 1.,
+            turn_speed: 1.,
+            infectiousness: 1.,
+            burst_size: 1.,
+        }
+    }
+}
+
+impl Genome {
+    pub fn mutate(&self, rng: &mut impl Rng) -> Self {
+        let mut drift = |value: f32| {
+            (value * rng.random_range(1. - MUTATION..1. + MUTATION)).clamp(MIN_TRAIT, MAX_TRAIT)
+        };
+        Genome {
+            speed: drift(self.speed),
+            turn_speed: drift(self.turn_speed),
+            infectiousness: drift(self.infectiousness),
+            burst_size: drift(self.burst_size),
+        }
+    }
+
+    fn traits(&self) -> [f32; 4] {
+        [
+            self.speed,
+            self.turn_speed,
+            self.infectiousness,
+            self.burst_size,
+        ]
+    }
+}
+
+// The genomes of enemies that managed to multiply, by archetype. New waves are
+// bred from them, so whatever gets past the player comes back stronger.
+#[derive(Resource, Default)]
+pub struct GenePool {
+    genomes: HashMap<String, Vec<Genome>>,
+}
+
+impl GenePool {
+    pub fn record(&mut self, enemy_type: &str, genome: Genome) {
+        let genomes = self.genomes.entry(enemy_type.to_string()).or_default();
+        if genomes.len() == GENE_POOL_SIZE {
+            genomes.remove(0);
+        }
+        genomes.push(genome);
+    }
+
+    // A mutation of one of the successful genomes, or the archetype as it is
+    // if none of its kind has multiplied yet.
+    pub fn draw(&self, enemy_type: &str, rng: &mut impl Rng) -> Genome {
+        self.genomes
+            .get(enemy_type)
+            .and_then(|genomes| genomes.choose(rng))
+            .map_or_else(Genome::default, |genome| genome.mutate(rng))
+    }
+}
+
+// Everything spawned during one wave, of one archetype.
+struct WaveTraits {
+    wave: i32,
+    enemy_type: String,
+    count: u32,
+    totals: [f32; 4],
+}
+
+// How the traits of every enemy spawned during the run added up, wave by wave.
+#[derive(Resource, Default)]
+pub struct Evolution {
+    waves: Vec<WaveTraits>,
+}
+
+impl Evolution {
+    pub fn record(&mut self, wave: i32, enemy_type: &str, genome: &Genome) {
+        let index = match self
+            .waves
+            .iter()
+            .position(|traits| traits.wave == wave && traits.enemy_type == enemy_type)
+        {
+            Some(index) => index,
+            None => {
+                self.waves.push(WaveTraits {
+                    wave,
+                    enemy_type: enemy_type.to_string(),
+                    count: 0,
+                    totals: [0.; 4],
+                });
+                self.waves.len() - 1
+            }
+        };
+        let traits = &mut self.waves[index];
+        traits.count += 1;
+        for (total, value) in traits.totals.iter_mut().zip(genome.traits()) {
+            *total += value;
+        }
+    }
+
+    // One line per wave and archetype, with the average of every trait.
+    pub fn summary(&self) -> Vec<String> {
+        self.waves
+            .iter()
+            .map(|traits| {
+                let [speed, turn_speed, infectiousness, burst_size] =
+                    traits.totals.map(|total| total / traits.count as f32);
+                format!(
+                    "Level {} {} ({}): speed x{:.2}, turning x{:.2}, infectiousness x{:.2}, burst x{:.2}",
+                    traits.wave,
+                    traits.enemy_type,
+                    traits.count,
+                    speed,
+                    turn_speed,
+                    infectiousness,
+                    burst_size
+                )
+            })
+            .collect()
+    }
+
+    // The summary cut down to `lines` lines, keeping how the run started and
+    // how it ended.
+    pub fn short_summary(&self, lines: usize) -> Vec<String> {
+        let mut summary = self.summary();
+        if summary.len() > lines {
+            let head = lines / 2;
+            let tail = lines - head - 1;
+            summary.drain(head..summary.len() - tail);
+            summary.insert(head, "...".to_string());
+        }
+        summary
+    }
+}
+
+// Counts towards the wave being fought, the spawner has already moved on to
+// the next one by the time the first enemies of a wave show up.
+fn record_genome(
+    trigger: Trigger<OnAdd, Genome>,
+    mut evolution: ResMut<Evolution>,
+    spawner: Query<&EnemySpawner>,
+    enemies: Query<(&Genome, &EnemyType)>,
+) {
+    let (Ok(spawner), Ok((genome, enemy_type))) = (spawner.single(), enemies.get(trigger.target()))
+    else {
+        return;
+    };
+    evolution.record(spawner.wave - 1, &enemy_type.0, genome);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetype::VIRUS;
+
+    #[test]
+    fn summary_averages_each_wave() {
+        let mut evolution = Evolution::default();
+        let slow = Genome {
+            speed: 0.5,
+            ..default()
+        };
+        evolution.record(1, VIRUS, &Genome::default());
+        evolution.record(1, VIRUS, &slow);
+        evolution.record(2, VIRUS, &slow);
+
+        assert_eq!(
+            evolution.summary(),
+            vec![
+                "Level 1 virus (2): speed x0.75, turning x1.00, infectiousness x1.00, burst x1.00",
+                "Level 2 virus (1): speed x0.50, turning x1.00, infectiousness x1.00, burst x1.00",
+            ]
+        );
+
+        for wave in 3..10 {
+            evolution.record(wave, VIRUS, &slow);
+        }
+        let short = evolution.short_summary(5);
+        assert_eq!(short.len(), 5);
+        assert!(short[0].starts_with("Level 1 "));
+        assert!(short[1].starts_with("Level 2 "));
+        assert_eq!(short[2], "...");
+        assert!(short[4].starts_with("Level 9 "));
+    }
+}
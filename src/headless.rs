@@ -0,0 +1,104 @@
+use std::time::Duration;
+
+use avian2d::prelude::*;
+use bevy::input::InputPlugin;
+use bevy::log::LogPlugin;
+use bevy::prelude::*;
+use bevy::scene::SceneSpawner;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::archetype::ArchetypePlugin;
+use crate::arena::ArenaPlugin;
+use crate::bacteria::BacteriaPlugin;
+use crate::controls::ControlsPlugin;
+use crate::enemy::EnemyPlugin;
+use crate::flow::FlowPlugin;
+use crate::genome::{Evolution, GenomePlugin};
+use crate::health::HealthPlugin;
+use crate::host::HostPlugin;
+use crate::level::{EnemySpawner, LevelPlugin};
+use crate::movement::MovementPlugin;
+use crate::navigation::NavigationPlugin;
+use crate::player::PlayerPlugin;
+use crate::player_attack::PlayerAttackPlugin;
+use crate::replay::ReplayPlugin;
+use crate::rng::{GameRng, RngPlugin};
+use crate::schedule::{GameState, SchedulePlugin};
+use crate::score::Score;
+use crate::spatial::SpatialPlugin;
+
+// Each update advances the simulation by exactly one fixed gameplay step (the
+// default 64Hz) no matter how long the frame actually took, so runs go as fast
+// as the machine allows.
+pub const SIMULATION_TIMESTEP: Duration = Duration::from_micros(15_625);
+
+// The gameplay rules without a window, renderer, UI or level, starting straight
+// into a run. Tests use this to set up their own scenes.
+pub fn base_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, StatesPlugin, InputPlugin, TransformPlugin))
+        .add_plugins(PhysicsPlugins::default())
+        .add_plugins((
+            ControlsPlugin,
+            MovementPlugin,
+            PlayerPlugin,
+            PlayerAttackPlugin,
+            SchedulePlugin,
+            RngPlugin,
+            EnemyPlugin,
+            BacteriaPlugin,
+            GenomePlugin,
+            HealthPlugin,
+            HostPlugin,
+            ArenaPlugin,
+            FlowPlugin,
+            SpatialPlugin,
+            NavigationPlugin,
+        ))
+        .add_plugins(ArchetypePlugin)
+        .init_resource::<Score>()
+        // Avian looks for scenes to build colliders from, but nothing spawns any.
+        .init_resource::<SceneSpawner>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(SIMULATION_TIMESTEP));
+
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+    app
+}
+
+// A full run without a window. High scores are not recorded.
+pub fn gameplay_app() -> App {
+    let mut app = base_app();
+    app.add_plugins(LevelPlugin);
+    app
+}
+
+// Plays a single run to the end and reports how it went. Pass `--replay <file>`
+// to check what a recorded run comes to.
+pub fn simulation_app() -> App {
+    let mut app = gameplay_app();
+    app.add_plugins((LogPlugin::default(), ReplayPlugin { record: false }))
+        .add_systems(OnEnter(GameState::GameOver), report_run);
+    app
+}
+
+fn report_run(
+    spawner: Single<&EnemySpawner>,
+    score: Res<Score>,
+    rng: Res<GameRng>,
+    evolution: Res<Evolution>,
+    mut exit: EventWriter<AppExit>,
+) {
+    info!(
+        "Seed {} reached level {} with {} points",
+        rng.seed(),
+        spawner.wave - 1,
+        score.points
+    );
+    for line in evolution.summary() {
+        info!("{}", line);
+    }
+    exit.write(AppExit::Success);
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+
+use crate::schedule::{GameState, InGameSet};
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>();
+        // Collisions from the last physics step are in by now.
+        app.add_systems(
+            FixedUpdate,
+            apply_damage.in_set(InGameSet::CollisionDetection),
+        );
+        app.add_systems(
+            FixedUpdate,
+            handle_deaths.in_set(InGameSet::DespawnEntities),
+        );
+        app.add_systems(Update, blink_invulnerable.in_set(InGameSet::EntityUpdates));
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+#[require(OnDeath)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+    // How long it shrugs off any more damage after taking a hit.
+    pub grace_secs: f32,
+    pub invulnerable_secs: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Health {
+            current: max,
+            max,
+            grace_secs: 0.,
+            invulnerable_secs: 0.,
+        }
+    }
+
+    pub fn with_grace(mut self, grace_secs: f32) -> Self {
+        self.grace_secs = grace_secs;
+        self
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
+
+// What happens once an entity runs out of health.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub enum OnDeath {
+    #[default]
+    Despawn,
+    // Losing it loses the run.
+    EndRun,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct DamageEvent {
+    pub target: Entity,
+    pub amount: f32,
+    pub source: Option<Entity>,
+}
+
+// Triggered on an entity as it dies, before it is despawned, with whatever
+// dealt the final blow.
+#[derive(Event)]
+pub struct Died {
+    pub killer: Option<Entity>,
+}
+
+// Left on whatever died without being despawned, so it only dies once.
+#[derive(Component)]
+pub struct Dead;
+
+// Who hit it last, to credit the kill to.
+#[derive(Component)]
+struct LastHitBy(Option<Entity>);
+
+fn apply_damage(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut events: EventReader<DamageEvent>,
+    mut healths: Query<&mut Health>,
+) {
+    for mut health in &mut healths {
+        if health.invulnerable_secs > 0. {
+            health.invulnerable_secs = (health.invulnerable_secs - time.delta_secs()).max(0.);
+        }
+    }
+
+    for event in events.read() {
+        let Ok(mut health) = healths.get_mut(event.target) else {
+            continue;
+        };
+        if health.invulnerable_secs > 0. || health.is_dead() {
+            continue;
+        }
+        health.current -= event.amount;
+        health.invulnerable_secs = health.grace_secs;
+        commands
+            .entity(event.target)
+            .insert(LastHitBy(event.source));
+    }
+}
+
+fn handle_deaths(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<GameState>>,
+    dead: Query<(Entity, &Health, &OnDeath, Option<&LastHitBy>), Without<Dead>>,
+) {
+    for (entity, health, on_death, last_hit) in &dead {
+        if !health.is_dead() {
+            continue;
+        }
+        let killer = last_hit.and_then(|last_hit| last_hit.0);
+        commands.trigger_targets(Died { killer }, entity);
+        match on_death {
+            OnDeath::Despawn => {
+                commands.entity(entity).try_despawn();
+            }
+            OnDeath::EndRun => {
+                commands.entity(entity).insert(Dead);
+                next_state.set(GameState::GameOver);
+            }
+        }
+    }
+}
+
+const BLINKS_PER_SECOND: f32 = 8.;
+
+fn blink_invulnerable(mut query: Query<(&Health, &mut Sprite), Changed<Health>>) {
+    for (health, mut sprite) in &mut query {
+        let visible = (health.invulnerable_secs * BLINKS_PER_SECOND * 2.) as i32 % 2 == 0;
+        sprite.color.set_alpha(if visible { 1. } else { 0.3 });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{run_frames, spawn_host, spawn_player, test_app};
+
+    #[derive(Resource, Default)]
+    struct Deaths(usize);
+
+    fn count_deaths(_trigger: Trigger<Died>, mut deaths: ResMut<Deaths>) {
+        deaths.0 += 1;
+    }
+
+    fn hit(app: &mut App, target: Entity, amount: f32) {
+        app.world_mut().send_event(DamageEvent {
+            target,
+            amount,
+            source: None,
+        });
+        run_frames(app, 1);
+    }
+
+    #[test]
+    fn hits_in_quick_succession_only_count_once() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        run_frames(&mut app, 1);
+        let max = app.world().get::<Health>(player).unwrap().max;
+
+        hit(&mut app, player, 1.);
+        hit(&mut app, player, 1.);
+        assert_eq!(app.world().get::<Health>(player).unwrap().current, max - 1.);
+
+        // Once the grace period is over it can be hurt again.
+        run_frames(&mut app, 64);
+        hit(&mut app, player, 1.);
+        assert_eq!(app.world().get::<Health>(player).unwrap().current, max - 2.);
+    }
+
+    #[test]
+    fn player_death_ends_the_run() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        app.init_resource::<Deaths>().add_observer(count_deaths);
+        run_frames(&mut app, 1);
+
+        hit(&mut app, player, 100.);
+        run_frames(&mut app, 1);
+        assert_eq!(
+            *app.world().resource::<State<GameState>>().get(),
+            GameState::GameOver
+        );
+        assert!(app.world().get_entity(player).is_ok());
+
+        // Even once playing on, it doesn't die all over again.
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(GameState::Playing);
+        run_frames(&mut app, 3);
+        assert_eq!(app.world().resource::<Deaths>().0, 1);
+    }
+}
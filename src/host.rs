@@ -5,60 +5,88 @@ use std::f32::consts::PI;
 
 pub struct HostPlugin;
 
-use crate::enemy::{create_virus, Hostile, Targeting, VirusAttached};
+use crate::archetype::{EnemyRegistry, EnemyType, Offspring};
+use crate::enemy::{create_enemy, Hostile, Targeting, VirusAttached};
+use crate::genome::{GenePool, Genome};
+use crate::health::{DamageEvent, Died, Health};
 use crate::movement::Velocity;
-use crate::schedule::InGameSet;
+use crate::rng::GameRng;
+use crate::schedule::{GameState, InGameSet};
 
 impl Plugin for HostPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, (decay_cell).in_set(InGameSet::EntityUpdates));
-        app.add_systems(Update, (cells_die).in_set(InGameSet::DespawnEntities));
+        app.add_systems(FixedUpdate, (decay_cell).in_set(InGameSet::EntityUpdates));
+        app.add_systems(
+            FixedUpdate,
+            check_tissue_destroyed.in_set(InGameSet::DespawnEntities),
+        );
+        app.add_observer(cells_die);
     }
 }
 
 #[derive(Component, Clone)]
+#[require(RigidBody = RigidBody::Static, Health = Health::new(HOST_HEALTH))]
 pub struct Host;
 
+pub const HOST_HEALTH: f32 = 10.0;
+// An infection wears a healthy host down to nothing in this long.
+const SECONDS_TO_BURST: f32 = 7.0;
+
 #[derive(Component, Clone)]
 pub struct Infected {
-    current_seconds_to_death: f32,
-    initial_seconds_to_death: f32,
     decay_multiplier: f32,
+    // The archetype that bursts out and the genome it inherits, those of the
+    // first virus to get in.
+    offspring_type: String,
+    genome: Genome,
     num_offspring: i32,
     max_offspring: i32,
 }
 
-impl Default for Infected {
-    fn default() -> Self {
+impl Infected {
+    pub fn new(offspring: &Offspring, genome: &Genome) -> Self {
         Infected {
-            current_seconds_to_death: 7.0,
-            initial_seconds_to_death: 7.0,
-            decay_multiplier: 1.0,
-            num_offspring: 4,
-            max_offspring: 100,
+            decay_multiplier: genome.infectiousness,
+            offspring_type: offspring.archetype.clone(),
+            genome: *genome,
+            num_offspring: ((offspring.count as f32 * genome.burst_size).round() as i32).max(1),
+            max_offspring: offspring.max,
         }
     }
 }
 
 #[derive(Component, Clone)]
-struct InfectionIndicator;
+pub struct InfectionIndicator;
 
 pub fn handle_infection(
     trigger: Trigger<OnCollisionStart>,
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut enemies: Query<(&Hostile, &mut Velocity, &mut Transform), Without<VirusAttached>>,
+    registry: Res<EnemyRegistry>,
+    meshes: Option<ResMut<Assets<Mesh>>>,
+    materials: Option<ResMut<Assets<ColorMaterial>>>,
+    mut enemies: Query<
+        (&Hostile, &EnemyType, &Genome, &mut Velocity, &mut Transform),
+        Without<VirusAttached>,
+    >,
     mut host: Query<(&Transform, Option<&mut Infected>), Without<Hostile>>,
 ) {
-    let Ok((hostile, mut velocity, mut transform)) = enemies.get_mut(trigger.collider) else {
+    let Ok((hostile, enemy_type, genome, mut velocity, mut transform)) =
+        enemies.get_mut(trigger.collider)
+    else {
+        return;
+    };
+    // Nothing would come out of a host it infected.
+    let Some(offspring) = &registry.get(&enemy_type.0).offspring else {
         return;
     };
 
     if let Hostile::InfectThenDie = hostile {
         velocity.value = Vec3::ZERO;
-        commands.entity(trigger.collider).insert(VirusAttached);
-        commands.entity(trigger.collider).remove::<Targeting>();
+        // From now on it is part of the host's body and only moves with it.
+        commands
+            .entity(trigger.collider)
+            .insert(VirusAttached)
+            .remove::<(Targeting, RigidBody, TransformInterpolation)>();
 
         if let Ok((parent_transform, infected)) = host.get_mut(trigger.target()) {
             transform.translation -= parent_transform.translation;
@@ -75,18 +103,23 @@ pub fn handle_infection(
                 _ => {
                     commands
                         .entity(trigger.target())
-                        .insert(Infected::default());
+                        .insert(Infected::new(offspring, genome));
 
-                    commands
-                        .spawn((
-                            InfectionIndicator,
+                    let mut indicator = commands.spawn((
+                        InfectionIndicator,
+                        Transform::from_xyz(0.0, 0.0, 1.0),
+                        ChildOf(trigger.target()),
+                    ));
+
+                    // Headless simulations have no mesh or material assets.
+                    if let (Some(mut meshes), Some(mut materials)) = (meshes, materials) {
+                        indicator.insert((
                             Mesh2d(meshes.add(Circle::new(15.0))),
                             MeshMaterial2d(
                                 materials.add(Color::Srgba(Srgba::new(1.0, 0.0, 0.0, 1.0))),
                             ),
-                            Transform::from_xyz(0.0, 0.0, 1.0),
-                        ))
-                        .insert(ChildOf(trigger.target()));
+                        ));
+                    }
                 }
             };
         };
@@ -99,18 +132,25 @@ pub fn handle_infection(
 
 fn decay_cell(
     time: Res<Time>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut q_parent: Query<(&mut Infected, &Children)>,
-    mut q_child: Query<(&mut Mesh2d, &mut Transform), With<InfectionIndicator>>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut damage: EventWriter<DamageEvent>,
+    q_parent: Query<(Entity, &Infected, &Health, Option<&Children>)>,
+    mut q_child: Query<(Option<&mut Mesh2d>, &mut Transform), With<InfectionIndicator>>,
 ) {
-    for (mut infection, children) in q_parent.iter_mut() {
-        infection.current_seconds_to_death -= infection.decay_multiplier * time.delta_secs();
-
-        for child in children.iter() {
-            if let Ok((mut mesh2d_handle, mut transform)) = q_child.get_mut(child) {
-                let ratio = infection.current_seconds_to_death / infection.initial_seconds_to_death;
-                // TODO: Is it ok to keep adding meshes or should I edit them in place?
-                mesh2d_handle.0 = meshes.add(CircularSector::from_turns(10.0, ratio));
+    for (host, infection, health, children) in q_parent.iter() {
+        damage.write(DamageEvent {
+            target: host,
+            amount: health.max / SECONDS_TO_BURST * infection.decay_multiplier * time.delta_secs(),
+            source: None,
+        });
+
+        for child in children.into_iter().flatten() {
+            if let Ok((mesh2d_handle, mut transform)) = q_child.get_mut(*child) {
+                let ratio = health.current / health.max;
+                if let (Some(mut mesh2d_handle), Some(meshes)) = (mesh2d_handle, meshes.as_mut()) {
+                    // TODO: Is it ok to keep adding meshes or should I edit them in place?
+                    mesh2d_handle.0 = meshes.add(CircularSector::from_turns(10.0, ratio));
+                }
                 // Make one edge of the sector vertical.
                 // rotate by 2*PI*ratio/2 radians.
                 // Start from no rotation.
@@ -121,27 +161,130 @@ fn decay_cell(
     }
 }
 
-// TODO: make this system events based.
+// However an infected host dies, worn down by the infection or bitten to
+// death, it bursts and lets out what grew inside.
 fn cells_die(
+    trigger: Trigger<Died>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    query: Query<(Entity, &Infected, &Transform)>,
+    mut rng: ResMut<GameRng>,
+    asset_server: Option<Res<AssetServer>>,
+    registry: Res<EnemyRegistry>,
+    mut gene_pool: ResMut<GenePool>,
+    query: Query<(&Infected, &Transform)>,
 ) {
-    for (entity, infected, transform) in query.iter() {
-        if infected.current_seconds_to_death > 0.0 {
-            continue;
-        }
-
-        let mut rng = rand::rng();
+    let Ok((infected, transform)) = query.get(trigger.target()) else {
+        return;
+    };
+    // It got through, future waves take after it.
+    gene_pool.record(&infected.offspring_type, infected.genome);
 
-        for _ in 0..infected.num_offspring {
-            let random_direction = Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
-            commands.spawn(create_virus(
-                &asset_server,
+    for _ in 0..infected.num_offspring {
+        let random_direction = Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
+        let genome = infected.genome.mutate(&mut **rng);
+        commands.spawn(
+            create_enemy(
+                &registry,
+                asset_server.as_deref(),
+                &infected.offspring_type,
                 random_direction,
                 transform.translation.xy(),
-            ));
+            )
+            .with_genome(genome),
+        );
+    }
+}
+
+// The run is lost once every host cell has burst.
+fn check_tissue_destroyed(
+    hosts: Query<(), With<Host>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if hosts.is_empty() {
+        next_state.set(GameState::GameOver);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetype::VIRUS;
+    use crate::movement::Speed;
+    use crate::test_harness::{count, run_frames, spawn_host, spawn_virus, test_app};
+
+    #[test]
+    fn virus_attaches_to_host_and_infects_it() {
+        let mut app = test_app();
+        let host = spawn_host(&mut app, Vec2::ZERO);
+        let virus = spawn_virus(&mut app, Vec2::new(15., 0.), Vec2::NEG_X);
+        run_frames(&mut app, 3);
+
+        assert!(app.world().get::<VirusAttached>(virus).is_some());
+        assert_eq!(
+            app.world().get::<ChildOf>(virus).map(ChildOf::parent),
+            Some(host)
+        );
+        assert!(app.world().get::<Infected>(host).is_some());
+        assert_eq!(count::<With<InfectionIndicator>>(&mut app), 1);
+    }
+
+    #[test]
+    fn bursting_host_releases_its_offspring() {
+        let mut app = test_app();
+        let host = spawn_host(&mut app, Vec2::ZERO);
+        // Keep the run going after the first host bursts.
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        app.world_mut().get_mut::<Health>(host).unwrap().current = 0.1;
+        app.world_mut().entity_mut(host).insert(Infected {
+            decay_multiplier: 1.0,
+            offspring_type: VIRUS.to_string(),
+            genome: Genome::default(),
+            num_offspring: 6,
+            max_offspring: 100,
+        });
+        app.world_mut()
+            .spawn((InfectionIndicator, Transform::default(), ChildOf(host)));
+        run_frames(&mut app, 10);
+
+        assert!(app.world().get_entity(host).is_err());
+        assert_eq!(count::<With<InfectionIndicator>>(&mut app), 0);
+        assert_eq!(count::<With<Hostile>>(&mut app), 6);
+    }
+
+    #[test]
+    fn offspring_inherit_a_mutated_genome() {
+        let mut app = test_app();
+        let host = spawn_host(&mut app, Vec2::ZERO);
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let parent = Genome {
+            speed: 2.,
+            ..default()
+        };
+        let registry = EnemyRegistry::default();
+        let offspring = registry.get(VIRUS).offspring.as_ref().unwrap();
+        app.world_mut().get_mut::<Health>(host).unwrap().current = 0.1;
+        app.world_mut()
+            .entity_mut(host)
+            .insert(Infected::new(offspring, &parent));
+        app.world_mut()
+            .spawn((InfectionIndicator, Transform::default(), ChildOf(host)));
+        run_frames(&mut app, 10);
+
+        let offspring: Vec<(Genome, f32)> = app
+            .world_mut()
+            .query::<(&Genome, &Speed)>()
+            .iter(app.world())
+            .map(|(genome, speed)| (*genome, speed.default))
+            .collect();
+        assert!(!offspring.is_empty());
+        for (genome, speed) in &offspring {
+            assert!((genome.speed - 2.).abs() <= 0.2);
+            assert!((speed - 20. * genome.speed).abs() < 0.01);
         }
-        commands.entity(entity).despawn();
+        // Each one mutated on its own.
+        assert_ne!(offspring[0].0, offspring[1].0);
+        // And the one that got them out is remembered for later waves.
+        let mut rng = StdRng::seed_from_u64(0);
+        let drawn = app.world().resource::<GenePool>().draw(VIRUS, &mut rng);
+        assert!((drawn.speed - 2.).abs() <= 0.2);
     }
 }
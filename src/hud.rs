@@ -1,36 +1,60 @@
 use bevy::prelude::*;
 
+use crate::controls::{key_label, InputDevice, KeyBindings, MouseSteering, RebindableKey};
+use crate::health::Health;
 use crate::level::EnemySpawner;
-use crate::player_attack::{
-    BoostBar, DuplicationBar, DuplicationCharge, PlayerChargingGUI, CHARGEBAR_WIDTH,
-};
+use crate::player::Player;
+use crate::player_attack::{BoostBar, DuplicationBar, CHARGEBAR_WIDTH};
 use crate::schedule::InGameSet;
+use crate::score::Score;
 
 pub struct HUDPlugin;
 
 impl Plugin for HUDPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, setup_hud);
-        app.add_systems(Update, update_wave_text.in_set(InGameSet::EntityUpdates));
+        app.add_systems(
+            Update,
+            (update_wave_text, update_score_text, update_health_text)
+                .in_set(InGameSet::EntityUpdates),
+        );
+        app.add_systems(
+            Update,
+            update_control_hints.run_if(
+                resource_changed::<InputDevice>
+                    .or(resource_changed::<KeyBindings>)
+                    .or(resource_changed::<MouseSteering>),
+            ),
+        );
     }
 }
 
-fn setup_hud(mut commands: Commands) {
-    commands.insert_resource(PlayerChargingGUI {
-        current_boost_level: 0.,
-        max_boost_level: 2.,
-    });
-
-    commands.insert_resource(DuplicationCharge {
-        current_progress: 0.,
-        max_progress: 0.5, //4.0,
-    });
-
+fn setup_hud(mut commands: Commands, keys: Res<KeyBindings>, steering: Res<MouseSteering>) {
     commands.spawn((
         Text::new("Level: "),
         children![(TextSpan::default(), WaveText)],
     ));
 
+    commands.spawn((
+        Text::new("Health: "),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(25.),
+            ..default()
+        },
+        children![(TextSpan::default(), HealthText)],
+    ));
+
+    commands.spawn((
+        Text::new("Score: "),
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.),
+            ..default()
+        },
+        children![(TextSpan::default(), ScoreText)],
+    ));
+
     commands.spawn((
         Node {
             position_type: PositionType::Absolute,
@@ -64,7 +88,8 @@ fn setup_hud(mut commands: Commands) {
                         BackgroundColor(Color::Oklcha(Oklcha::lch(0.5, 0.5, 0.5))),
                     ),
                     (
-                        Text::new("Hold space then release to boost "),
+                        Text::new(ControlHint::Boost.text(InputDevice::Keyboard, &keys, *steering)),
+                        ControlHint::Boost,
                         Node {
                             position_type: PositionType::Absolute,
                             ..default()
@@ -94,7 +119,12 @@ fn setup_hud(mut commands: Commands) {
                         BackgroundColor(Color::Oklcha(Oklcha::lch(0.44, 0.06, 245.0))),
                     ),
                     (
-                        Text::new("Hold shift to self-replicate"),
+                        Text::new(ControlHint::Replicate.text(
+                            InputDevice::Keyboard,
+                            &keys,
+                            *steering
+                        )),
+                        ControlHint::Replicate,
                         Node {
                             position_type: PositionType::Absolute,
                             ..default()
@@ -109,7 +139,8 @@ fn setup_hud(mut commands: Commands) {
                     color: Color::WHITE,
                     offset: Val::Px(0.0),
                 },
-                Text::new("Move with arrow keys"),
+                Text::new(ControlHint::Move.text(InputDevice::Keyboard, &keys, *steering)),
+                ControlHint::Move,
             ),
         ],
     ));
@@ -134,3 +165,94 @@ fn update_wave_text(
         };
     }
 }
+
+#[derive(Component)]
+struct ScoreText;
+
+fn update_score_text(score: Res<Score>, mut score_text: Query<&mut TextSpan, With<ScoreText>>) {
+    for mut text in &mut score_text {
+        text.0 = format!("{}", score.points);
+    }
+}
+
+#[derive(Component)]
+struct HealthText;
+
+fn update_health_text(
+    player: Single<&Health, With<Player>>,
+    mut health_text: Query<&mut TextSpan, With<HealthText>>,
+) {
+    for mut text in &mut health_text {
+        text.0 = format!("{} / {}", player.current.max(0.), player.max);
+    }
+}
+
+#[derive(Component, Clone, Copy)]
+enum ControlHint {
+    Move,
+    Boost,
+    Replicate,
+}
+
+impl ControlHint {
+    fn text(self, device: InputDevice, keys: &KeyBindings, steering: MouseSteering) -> String {
+        match (self, device) {
+            (ControlHint::Move, InputDevice::Keyboard) if steering.0 => {
+                "Move with the mouse".to_string()
+            }
+            (ControlHint::Boost, InputDevice::Keyboard) if steering.0 => format!(
+                "Hold click or {} then release to dash ",
+                key_label(keys.get(RebindableKey::Boost))
+            ),
+            (ControlHint::Move, InputDevice::Keyboard) => {
+                if *keys == KeyBindings::default() {
+                    "Move with arrow keys".to_string()
+                } else {
+                    let move_keys: Vec<String> = [
+                        RebindableKey::MoveUp,
+                        RebindableKey::MoveLeft,
+                        RebindableKey::MoveDown,
+                        RebindableKey::MoveRight,
+                    ]
+                    .into_iter()
+                    .map(|action| key_label(keys.get(action)))
+                    .collect();
+                    format!("Move with {}", move_keys.join("/"))
+                }
+            }
+            (ControlHint::Move, InputDevice::Gamepad) => "Move with the left stick".to_string(),
+            (ControlHint::Move, InputDevice::Touch) => "Move with the joystick".to_string(),
+            (ControlHint::Boost, InputDevice::Keyboard) => format!(
+                "Hold {} then release to boost ",
+                key_label(keys.get(RebindableKey::Boost))
+            ),
+            (ControlHint::Boost, InputDevice::Gamepad) => {
+                "Hold (A) or RT then release to boost ".to_string()
+            }
+            (ControlHint::Boost, InputDevice::Touch) => {
+                "Hold Boost then release to boost ".to_string()
+            }
+            (ControlHint::Replicate, InputDevice::Keyboard) => format!(
+                "Hold {} to self-replicate",
+                key_label(keys.get(RebindableKey::Replicate))
+            ),
+            (ControlHint::Replicate, InputDevice::Gamepad) => {
+                "Hold LB to self-replicate".to_string()
+            }
+            (ControlHint::Replicate, InputDevice::Touch) => {
+                "Hold Replicate to self-replicate".to_string()
+            }
+        }
+    }
+}
+
+fn update_control_hints(
+    device: Res<InputDevice>,
+    keys: Res<KeyBindings>,
+    steering: Res<MouseSteering>,
+    mut hints: Query<(&mut Text, &ControlHint)>,
+) {
+    for (mut text, hint) in &mut hints {
+        text.0 = hint.text(*device, &keys, *steering);
+    }
+}
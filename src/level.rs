@@ -1,34 +1,96 @@
 use avian2d::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
 use rand::prelude::*;
+use serde::Deserialize;
 use std::f32::consts::PI;
 
-use crate::enemy::{create_virus, Hostile};
-use crate::host::{handle_infection, Host};
-use crate::movement::{Speed, Velocity};
-use crate::player::{handle_virus_collision, Player, PlayerBundle, WhiteBloodCellBundle};
-use crate::player_attack::PlayerActionParams;
+use crate::archetype::{EnemyRegistry, BACTERIA, VIRUS};
+use crate::arena::{vessel_wall, Arena, ArenaShape, VesselWall};
+use crate::assets::load_image;
+use crate::enemy::{create_enemy, Hostile};
+use crate::genome::{Evolution, GenePool};
+use crate::health::{Health, OnDeath};
+use crate::host::{handle_infection, Host, InfectionIndicator};
+use crate::movement::{FlowSusceptibility, Inertia, Speed, Velocity};
+use crate::player::{
+    handle_virus_collision, Player, PlayerBundle, WhiteBloodCell, WhiteBloodCellBundle,
+};
+use crate::player_attack::{DuplicationCharge, PlayerActionParams, PlayerChargingGUI, SeekVirus};
+use crate::rng::{reseed_rng, GameRng};
+use crate::schedule::{GameState, InGameSet};
+use crate::score::Score;
 
 pub struct LevelPlugin;
 
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, (spawn_player, setup_enemy_spawner, spawn_walls));
-        app.add_systems(Update, spawn_enemies);
+        app.insert_resource(LevelDescription::vessel().arena());
+        app.add_systems(Startup, spawn_camera);
+        // Resuming from the pause menu also enters `Playing`, so only build the
+        // level when there isn't one already.
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            (
+                reseed_rng,
+                (
+                    spawn_player,
+                    setup_enemy_spawner,
+                    spawn_walls,
+                    spawn_vessel_wall,
+                ),
+            )
+                .chain()
+                .run_if(not(any_with_component::<EnemySpawner>)),
+        );
+        // Leaving the game over screen restarts the run from scratch.
+        app.add_systems(OnExit(GameState::GameOver), despawn_level);
+        app.add_systems(FixedUpdate, spawn_enemies.in_set(InGameSet::EntityUpdates));
         app.insert_resource(ClearColor(Color::oklcha(0.72, 0.15, 15.8, 1.0)));
     }
 }
 
-fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
+// How a level is laid out, read from a file in `assets/levels`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct LevelDescription {
+    // The corners of the vessel wall, in order.
+    pub vessel_outline: Vec<(f32, f32)>,
+}
+
+impl LevelDescription {
+    // Built into the game rather than loaded as an asset, the level has to be
+    // there before the first run starts, headless runs included.
+    pub fn vessel() -> Self {
+        ron::from_str(include_str!("../assets/levels/vessel.ron"))
+            .expect("assets/levels/vessel.ron should describe a level")
+    }
+
+    pub fn arena(&self) -> Arena {
+        Arena {
+            shape: ArenaShape::Polygon(
+                self.vessel_outline
+                    .iter()
+                    .map(|&corner| corner.into())
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
     commands.spawn(Camera2d);
+}
 
+fn spawn_player(mut commands: Commands, asset_server: Option<Res<AssetServer>>) {
     commands.spawn((
         Sprite {
-            image: asset_server.load("bacteria.png"),
+            image: load_image(asset_server.as_deref(), "bacteria.png"),
             custom_size: Some(Vec2::splat(125.0)),
             ..default()
         },
         Transform::from_xyz(0.0, 100.0, 0.0),
+        Obstacle,
         children![(
             Transform::from_xyz(0.0, 20.0, 0.0),
             Collider::ellipse(13.0, 33.0),
@@ -36,31 +98,49 @@ fn spawn_player(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 
     commands
-        .spawn((PlayerBundle {
-            marker: Player,
-            action_params: PlayerActionParams {
-                boosted_speed: 300.,
-                remaining_secs: 0.,
-                extra_seconds_per_boost_level: 0.1,
-                extra_speed_per_boost_level: 50.,
-            },
-            white_blood_cell_bundle: WhiteBloodCellBundle {
-                sprite: Sprite {
-                    image: asset_server.load("white_blood_cell.png"),
-                    custom_size: Some(Vec2::splat(40.)),
-                    ..default()
-                },
-                transform: Transform::from_xyz(0., 0., 0.),
-                velocity: Velocity::new(Vec3::ZERO),
-                speed: Speed::new(150.),
-                collider: Collider::circle(20.0),
-                colliding_entities: CollidingEntities::default(),
-                collision_events: CollisionEventsEnabled,
-            },
-        },))
+        .spawn(create_player(asset_server.as_deref()))
         .observe(handle_virus_collision);
 }
 
+pub const PLAYER_HEALTH: f32 = 5.;
+
+pub fn create_player(asset_server: Option<&AssetServer>) -> PlayerBundle {
+    PlayerBundle {
+        marker: Player,
+        actions: Actions::default(),
+        action_params: PlayerActionParams {
+            boosted_speed: 300.,
+            remaining_secs: 0.,
+            extra_seconds_per_boost_level: 0.1,
+            extra_speed_per_boost_level: 50.,
+            dash_direction: None,
+            impulse_pending: false,
+        },
+        on_death: OnDeath::EndRun,
+        white_blood_cell_bundle: WhiteBloodCellBundle {
+            marker: WhiteBloodCell,
+            sprite: Sprite {
+                image: load_image(asset_server, "white_blood_cell.png"),
+                custom_size: Some(Vec2::splat(40.)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0., 0., 0.),
+            velocity: Velocity::new(Vec3::ZERO),
+            speed: Speed::new(150.),
+            inertia: Inertia::new(600., 3.),
+            flow_susceptibility: FlowSusceptibility(0.3),
+            collider: Collider::circle(20.0),
+            health: Health::new(PLAYER_HEALTH).with_grace(1.),
+            colliding_entities: CollidingEntities::default(),
+            collision_events: CollisionEventsEnabled,
+        },
+    }
+}
+
+#[derive(Component)]
+#[require(RigidBody = RigidBody::Static)]
+pub struct Obstacle;
+
 #[derive(Bundle, Clone)]
 pub struct WallCellBundle {
     pub sprite: Sprite,
@@ -69,12 +149,27 @@ pub struct WallCellBundle {
     pub host: Host,
 }
 
-fn random_rotate_cell(bundle: &mut WallCellBundle, rng: &mut ThreadRng) {
+fn random_rotate_cell(bundle: &mut WallCellBundle, rng: &mut impl Rng) {
     bundle.sprite.flip_x = rng.random_bool(0.5);
     bundle.sprite.flip_y = rng.random_bool(0.5);
 }
 
-fn spawn_walls(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn spawn_walls(
+    mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    asset_server: Option<Res<AssetServer>>,
+) {
+    let image = load_image(asset_server.as_deref(), "wall_cell.png");
+    for wall_cell in wall_cells(&mut **rng, image) {
+        commands
+            .spawn((wall_cell, CollisionEventsEnabled))
+            .observe(handle_infection);
+    }
+}
+
+// A grid of host cells with random gaps. There is always at least one, a level
+// without any would be lost before it started.
+fn wall_cells(rng: &mut impl Rng, image: Handle<Image>) -> Vec<WallCellBundle> {
     const BOX_GRID_WIDTH: i32 = 5;
     const BOX_GRID_HEIGHT: i32 = 5;
 
@@ -90,7 +185,7 @@ fn spawn_walls(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     let template = WallCellBundle {
         sprite: Sprite {
-            image: asset_server.load("wall_cell.png"),
+            image,
             custom_size: Some(Vec2::splat(SPRITE_WIDTH)),
             ..default()
         },
@@ -99,11 +194,11 @@ fn spawn_walls(mut commands: Commands, asset_server: Res<AssetServer>) {
         host: Host,
     };
 
-    let mut rng: ThreadRng = rand::rng();
-
+    let mut cells = Vec::new();
     for i in 0..BOX_GRID_HEIGHT {
         for j in 0..BOX_GRID_WIDTH {
-            if rng.random_bool(0.7) {
+            let last_slot = i == BOX_GRID_HEIGHT - 1 && j == BOX_GRID_WIDTH - 1;
+            if rng.random_bool(0.7) && !(last_slot && cells.is_empty()) {
                 continue;
             }
 
@@ -114,13 +209,15 @@ fn spawn_walls(mut commands: Commands, asset_server: Res<AssetServer>) {
                 i as f32 * (HITBOX_WIDTH + INTERCELL_GAP) + y_offset;
 
             // Randomize the appearance to make them all look different.
-            random_rotate_cell(&mut wall_cell, &mut rng);
-
-            commands
-                .spawn((wall_cell, CollisionEventsEnabled))
-                .observe(handle_infection);
+            random_rotate_cell(&mut wall_cell, rng);
+            cells.push(wall_cell);
         }
     }
+    cells
+}
+
+fn spawn_vessel_wall(mut commands: Commands, arena: Res<Arena>) {
+    commands.spawn(vessel_wall(&arena));
 }
 
 #[derive(Component)]
@@ -142,12 +239,24 @@ fn setup_enemy_spawner(mut commands: Commands) {
 
 const SECONDS_BETWEEN_WAVES: f32 = 3.0;
 
+// What waves are made from, none of it changed by spawning one.
+#[derive(SystemParam)]
+struct WaveSetup<'w> {
+    time: Res<'w, Time>,
+    asset_server: Option<Res<'w, AssetServer>>,
+    arena: Res<'w, Arena>,
+    registry: Res<'w, EnemyRegistry>,
+    gene_pool: Res<'w, GenePool>,
+}
+
 fn spawn_enemies(
-    time: Res<Time>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    setup: WaveSetup,
+    mut score: ResMut<Score>,
+    mut rng: ResMut<GameRng>,
     mut query: Query<&mut EnemySpawner>,
     remaining_enemies: Query<&Hostile>,
+    hosts: Query<(), With<Host>>,
 ) {
     let Ok(mut enemy_spawner) = query.single_mut() else {
         println!("Expected unique enemy spawner");
@@ -158,31 +267,226 @@ fn spawn_enemies(
         return;
     }
 
+    let WaveSetup {
+        time,
+        asset_server,
+        arena,
+        registry,
+        gene_pool,
+    } = setup;
     if enemy_spawner.timer_secs > 0.0 {
         enemy_spawner.timer_secs -= time.delta_secs();
         return;
     }
 
-    let mut rng = rand::rng();
+    if enemy_spawner.wave > 1 {
+        score.wave_survived(hosts.iter().len());
+    }
 
-    let cluster_origin = enemy_spawner.radius * Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
+    // Waves come from far away, but never from outside the vessel.
+    let cluster_origin = arena.clamp(
+        enemy_spawner.radius * Vec2::from_angle(rng.random_range(0.0..2.0 * PI)),
+        enemy_spawner.cluster_radius,
+    );
 
     let num_enemies = (enemy_spawner.wave as f32 + 1.5).powf(2.0);
+    // Every other wave from the third on brings along one more bacterium.
+    let num_bacteria = (enemy_spawner.wave - 1) / 2;
+    let enemies = std::iter::repeat_n(VIRUS, num_enemies.ceil() as usize)
+        .chain(std::iter::repeat_n(BACTERIA, num_bacteria as usize));
 
-    for _ in 0..num_enemies.ceil() as i32 {
+    for id in enemies {
         // Don't sample the circle uniformly it probably looks better
         // with more viruses near the center.
         let individual_offset = rng.random_range(0.0..enemy_spawner.cluster_radius)
             * Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
 
         let random_direction = Vec2::from_angle(rng.random_range(0.0..2.0 * PI));
-        commands.spawn(create_virus(
-            &asset_server,
-            random_direction,
-            cluster_origin + individual_offset,
-        ));
+        let margin = registry.get(id).sprite_size;
+        let genome = gene_pool.draw(id, &mut **rng);
+        commands.spawn(
+            create_enemy(
+                &registry,
+                asset_server.as_deref(),
+                id,
+                random_direction,
+                arena.clamp(cluster_origin + individual_offset, margin),
+            )
+            .with_genome(genome),
+        );
     }
 
     enemy_spawner.timer_secs = SECONDS_BETWEEN_WAVES;
     enemy_spawner.wave += 1;
 }
+
+// Everything a run spawns, and a restart has to clear away.
+type LevelEntity = Or<(
+    With<Player>,
+    With<SeekVirus>,
+    With<Hostile>,
+    With<Host>,
+    With<InfectionIndicator>,
+    With<EnemySpawner>,
+    With<Obstacle>,
+    With<VesselWall>,
+)>;
+
+fn despawn_level(
+    mut commands: Commands,
+    mut charging: ResMut<PlayerChargingGUI>,
+    mut duplication: ResMut<DuplicationCharge>,
+    mut score: ResMut<Score>,
+    mut gene_pool: ResMut<GenePool>,
+    mut evolution: ResMut<Evolution>,
+    level_entities: Query<Entity, LevelEntity>,
+) {
+    for entity in &level_entities {
+        // Children are despawned along with their parents, so some of these
+        // may already be gone.
+        commands.entity(entity).try_despawn();
+    }
+
+    *charging = PlayerChargingGUI::default();
+    *duplication = DuplicationCharge::default();
+    *score = Score::default();
+    *gene_pool = GenePool::default();
+    *evolution = Evolution::default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arena::ArenaPlugin;
+    use crate::headless::SIMULATION_TIMESTEP;
+    use crate::schedule::SchedulePlugin;
+    use crate::test_harness::count;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn entities(app: &App) -> u32 {
+        app.world().entities().len()
+    }
+
+    fn set_state(app: &mut App, state: GameState) {
+        app.world_mut()
+            .resource_mut::<NextState<GameState>>()
+            .set(state);
+        app.update();
+    }
+
+    fn level_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), StatesPlugin))
+            .init_asset::<Image>()
+            .init_resource::<PlayerChargingGUI>()
+            .init_resource::<DuplicationCharge>()
+            .init_resource::<Score>()
+            .init_resource::<EnemyRegistry>()
+            .init_resource::<GenePool>()
+            .init_resource::<Evolution>()
+            .insert_resource(GameRng::from_seed(7))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(SIMULATION_TIMESTEP))
+            .add_plugins((SchedulePlugin, ArenaPlugin, LevelPlugin));
+        app
+    }
+
+    // Makes every random choice come out the same way, here skipping every
+    // slot of the grid.
+    struct ZeroRng;
+
+    impl RngCore for ZeroRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
+        }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+    }
+
+    #[test]
+    fn there_is_always_a_host() {
+        assert!(ZeroRng.random_bool(0.7));
+        assert_eq!(wall_cells(&mut ZeroRng, Handle::default()).len(), 1);
+    }
+
+    #[test]
+    fn the_vessel_wall_surrounds_the_start() {
+        let arena = LevelDescription::vessel().arena();
+        // Wherever you head from the start, you run into the wall.
+        for i in 0..32 {
+            let far = 5000. * Vec2::from_angle(i as f32 * 2. * PI / 32.);
+            assert!(arena.clamp(far, 0.).length() < 1500.);
+        }
+    }
+
+    #[test]
+    fn viruses_spawn_inside_the_vessel() {
+        let mut app = level_app();
+        let arena = Arena {
+            shape: ArenaShape::Circle { radius: 400. },
+        };
+        app.insert_resource(arena.clone());
+        app.update();
+        set_state(&mut app, GameState::Playing);
+
+        assert_eq!(count::<With<VesselWall>>(&mut app), 1);
+        let positions: Vec<Vec2> = app
+            .world_mut()
+            .query_filtered::<&Transform, With<Hostile>>()
+            .iter(app.world())
+            .map(|transform| transform.translation.xy())
+            .collect();
+        assert!(!positions.is_empty());
+        for position in positions {
+            assert!(position.length() <= 400. - 20. + 0.01);
+        }
+    }
+
+    #[test]
+    fn restart_returns_to_fresh_level() {
+        let mut app = level_app();
+        app.update();
+
+        set_state(&mut app, GameState::Playing);
+        let baseline = entities(&app);
+        assert_eq!(count::<With<Player>>(&mut app), 1);
+        assert_eq!(count::<With<EnemySpawner>>(&mut app), 1);
+        assert!(count::<With<Hostile>>(&mut app) > 0);
+
+        for _ in 0..3 {
+            // Make a mess that the restart has to clean up.
+            app.world_mut().spawn(SeekVirus);
+            app.world_mut().spawn(Hostile::InfectThenDie);
+            app.world_mut()
+                .resource_mut::<PlayerChargingGUI>()
+                .current_boost_level = 1.;
+            app.world_mut()
+                .resource_mut::<DuplicationCharge>()
+                .current_progress = 0.25;
+
+            set_state(&mut app, GameState::GameOver);
+            set_state(&mut app, GameState::Playing);
+
+            assert_eq!(entities(&app), baseline);
+            assert_eq!(count::<With<Player>>(&mut app), 1);
+            assert_eq!(count::<With<SeekVirus>>(&mut app), 0);
+            assert_eq!(count::<With<EnemySpawner>>(&mut app), 1);
+            assert_eq!(
+                app.world()
+                    .resource::<PlayerChargingGUI>()
+                    .current_boost_level,
+                0.
+            );
+            assert_eq!(
+                app.world().resource::<DuplicationCharge>().current_progress,
+                0.
+            );
+        }
+    }
+}
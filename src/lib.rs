@@ -0,0 +1,29 @@
+// The game itself, shared by the binary and the benchmarks in `benches`.
+pub mod archetype;
+pub mod arena;
+pub mod assets;
+pub mod bacteria;
+pub mod controls;
+pub mod enemy;
+pub mod flow;
+pub mod genome;
+pub mod headless;
+pub mod health;
+pub mod host;
+pub mod hud;
+pub mod level;
+pub mod menu;
+pub mod movement;
+pub mod navigation;
+pub mod player;
+pub mod player_attack;
+pub mod rebinding;
+pub mod replay;
+pub mod rng;
+pub mod schedule;
+pub mod score;
+pub mod spatial;
+pub mod storage;
+#[cfg(test)]
+mod test_harness;
+pub mod touch;
@@ -1,35 +1,61 @@
-mod enemy;
-mod host;
-mod hud;
-mod level;
-mod movement;
-mod player;
-mod player_attack;
-mod schedule;
-
 use avian2d::prelude::*;
 use bevy::prelude::*;
-use enemy::EnemyPlugin;
-use host::HostPlugin;
-use hud::HUDPlugin;
-use level::LevelPlugin;
-use movement::MovementPlugin;
-use player::PlayerPlugin;
-use player_attack::PlayerAttackPlugin;
-use schedule::SchedulePlugin;
+use qualified_immunity::archetype::ArchetypePlugin;
+use qualified_immunity::arena::ArenaPlugin;
+use qualified_immunity::bacteria::BacteriaPlugin;
+use qualified_immunity::controls::ControlsPlugin;
+use qualified_immunity::enemy::EnemyPlugin;
+use qualified_immunity::flow::FlowPlugin;
+use qualified_immunity::genome::GenomePlugin;
+use qualified_immunity::health::HealthPlugin;
+use qualified_immunity::host::HostPlugin;
+use qualified_immunity::hud::HUDPlugin;
+use qualified_immunity::level::LevelPlugin;
+use qualified_immunity::menu::MenuPlugin;
+use qualified_immunity::movement::MovementPlugin;
+use qualified_immunity::navigation::NavigationPlugin;
+use qualified_immunity::player::PlayerPlugin;
+use qualified_immunity::player_attack::PlayerAttackPlugin;
+use qualified_immunity::rebinding::RebindingPlugin;
+use qualified_immunity::replay::ReplayPlugin;
+use qualified_immunity::rng::RngPlugin;
+use qualified_immunity::schedule::SchedulePlugin;
+use qualified_immunity::score::ScorePlugin;
+use qualified_immunity::spatial::SpatialPlugin;
+use qualified_immunity::touch::TouchPlugin;
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--headless") {
+        qualified_immunity::headless::simulation_app().run();
+        return;
+    }
+
     App::new()
         .add_plugins(DefaultPlugins)
         .add_plugins(PhysicsPlugins::default())
         .add_plugins(PhysicsDebugPlugin::default()) // DO NOT RELEASE
+        .add_plugins(ControlsPlugin)
         .add_plugins(MovementPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(PlayerAttackPlugin)
         .add_plugins(LevelPlugin)
+        .add_plugins(ArenaPlugin)
+        .add_plugins(FlowPlugin)
+        .add_plugins(SpatialPlugin)
+        .add_plugins(NavigationPlugin)
+        .add_plugins(ArchetypePlugin)
         .add_plugins(SchedulePlugin)
+        .add_plugins(RngPlugin)
         .add_plugins(EnemyPlugin)
+        .add_plugins(BacteriaPlugin)
+        .add_plugins(GenomePlugin)
+        .add_plugins(HealthPlugin)
         .add_plugins(HostPlugin)
         .add_plugins(HUDPlugin)
+        .add_plugins(TouchPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(RebindingPlugin)
+        .add_plugins(ScorePlugin)
+        .add_plugins(ReplayPlugin { record: true })
         .run();
 }
@@ -0,0 +1,168 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+use crate::genome::Evolution;
+use crate::level::EnemySpawner;
+use crate::schedule::GameState;
+use crate::score::{record_high_score, HighScores, Score};
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MainMenu), spawn_main_menu);
+        app.add_systems(
+            OnEnter(GameState::Paused),
+            (spawn_pause_menu, pause_physics),
+        );
+        app.add_systems(OnExit(GameState::Paused), unpause_physics);
+        app.add_systems(
+            OnEnter(GameState::GameOver),
+            (
+                spawn_game_over_screen.after(record_high_score),
+                pause_physics,
+            ),
+        );
+        app.add_systems(OnExit(GameState::GameOver), unpause_physics);
+        app.add_systems(OnEnter(GameState::Controls), pause_physics);
+        app.add_systems(OnExit(GameState::Controls), unpause_physics);
+        app.add_systems(
+            Update,
+            (
+                start_game.run_if(in_state(GameState::MainMenu).or(in_state(GameState::GameOver))),
+                toggle_pause.run_if(in_state(GameState::Playing).or(in_state(GameState::Paused))),
+            ),
+        );
+    }
+}
+
+const START_KEYBINDING: KeyCode = KeyCode::Enter;
+const PAUSE_KEYBINDING: KeyCode = KeyCode::Escape;
+const GAMEPAD_MENU_BUTTON: GamepadButton = GamepadButton::Start;
+
+pub fn menu_screen(state: GameState) -> impl Bundle {
+    (
+        StateScoped(state),
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            row_gap: Val::Px(20.0),
+            ..default()
+        },
+        BackgroundColor(Color::BLACK.with_alpha(0.6)),
+    )
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    commands.spawn((
+        menu_screen(GameState::MainMenu),
+        children![
+            (
+                Text::new("Qualified Immunity"),
+                TextFont::from_font_size(48.0),
+            ),
+            Text::new("Press Enter or Start, or tap, to begin"),
+            Text::new("Press C to change controls"),
+        ],
+    ));
+}
+
+fn spawn_pause_menu(mut commands: Commands) {
+    commands.spawn((
+        menu_screen(GameState::Paused),
+        children![
+            (Text::new("Paused"), TextFont::from_font_size(48.0)),
+            Text::new("Press Escape or Start to resume"),
+            Text::new("Press C to change controls"),
+        ],
+    ));
+}
+
+// Long runs only show the first and last few levels, to fit on screen.
+const EVOLUTION_LINES: usize = 9;
+
+fn spawn_game_over_screen(
+    mut commands: Commands,
+    spawner: Single<&EnemySpawner>,
+    score: Res<Score>,
+    high_scores: Res<HighScores>,
+    evolution: Res<Evolution>,
+) {
+    commands
+        .spawn(menu_screen(GameState::GameOver))
+        .with_children(|screen| {
+            screen.spawn((
+                Text::new("The tissue has been destroyed"),
+                TextFont::from_font_size(48.0),
+            ));
+            screen.spawn(Text::new(format!(
+                "Reached level {} with {} points",
+                spawner.wave - 1,
+                score.points
+            )));
+            screen.spawn(Text::new("High scores"));
+            for (rank, high_score) in high_scores.0.iter().enumerate() {
+                screen.spawn(Text::new(format!(
+                    "{}. {} points (level {})",
+                    rank + 1,
+                    high_score.points,
+                    high_score.wave
+                )));
+            }
+            screen.spawn(Text::new("How the enemies evolved"));
+            for line in evolution.short_summary(EVOLUTION_LINES) {
+                screen.spawn((Text::new(line), TextFont::from_font_size(14.0)));
+            }
+            screen.spawn(Text::new("Press Enter or Start, or tap, to play again"));
+        });
+}
+
+fn start_game(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    touches: Res<Touches>,
+    gamepads: Query<&Gamepad>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(START_KEYBINDING)
+        || touches.any_just_pressed()
+        || gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GAMEPAD_MENU_BUTTON))
+    {
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn toggle_pause(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    gamepads: Query<&Gamepad>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard.just_pressed(PAUSE_KEYBINDING)
+        && !gamepads
+            .iter()
+            .any(|gamepad| gamepad.just_pressed(GAMEPAD_MENU_BUTTON))
+    {
+        return;
+    }
+
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+// Collision observers would otherwise keep firing while the game is paused.
+fn pause_physics(mut time: ResMut<Time<Physics>>) {
+    time.pause();
+}
+
+fn unpause_physics(mut time: ResMut<Time<Physics>>) {
+    time.unpause();
+}
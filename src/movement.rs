@@ -1,8 +1,20 @@
+use avian2d::prelude::*;
 use bevy::prelude::*;
 
+use crate::flow::FlowField;
 use crate::schedule::InGameSet;
 
+// The velocity gameplay wants an entity to move at. Physics does the actual
+// moving, so walls and other cells get in the way. Anything that moves does so
+// in fixed steps, so it is eased in between them to look smooth at any refresh
+// rate.
 #[derive(Component, Debug)]
+#[require(
+    RigidBody = RigidBody::Dynamic,
+    LockedAxes = LockedAxes::ROTATION_LOCKED,
+    TransformInterpolation,
+    FlowSusceptibility = FlowSusceptibility(1.)
+)]
 pub struct Velocity {
     pub value: Vec3,
 }
@@ -16,6 +28,27 @@ pub struct Speed {
 #[derive(Component)]
 pub struct TurnSpeed(pub f32);
 
+// How an entity swims through plasma. It picks up speed toward its `Velocity`
+// by at most `acceleration` per second, and drag takes away `drag` of whatever
+// is beyond that, like the push of a boost, every second. Without it an entity
+// moves at its velocity straight away.
+#[derive(Component, Debug)]
+pub struct Inertia {
+    pub acceleration: f32,
+    pub drag: f32,
+}
+
+// How strongly the bloodstream carries an entity along, on top of the velocity
+// it wants. Unless told otherwise, entities drift with the current as it is.
+#[derive(Component, Debug)]
+pub struct FlowSusceptibility(pub f32);
+
+impl Inertia {
+    pub fn new(acceleration: f32, drag: f32) -> Self {
+        Self { acceleration, drag }
+    }
+}
+
 impl Speed {
     pub fn new(value: f32) -> Self {
         Self {
@@ -31,21 +64,67 @@ impl Velocity {
     }
 }
 
+// What a collider bumps into. Cells and viruses run into each other and into
+// everything on the default layer, like hosts and obstacles, but pass through
+// their own kind so swarms and clones don't jam up.
+#[derive(PhysicsLayer, Default)]
+pub enum GameLayer {
+    #[default]
+    Default,
+    Cell,
+    Virus,
+}
+
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
+        // Seen from above, nothing falls.
+        app.insert_resource(Gravity(Vec2::ZERO));
+        // After everything has decided where it wants to go this step.
         app.add_systems(
-            Update,
-            (update_position, update_rotation).in_set(InGameSet::EntityUpdates),
+            FixedUpdate,
+            (apply_velocity, update_rotation).in_set(InGameSet::Physics),
         );
     }
 }
 
-fn update_position(time: Res<Time>, mut sprite: Query<(&Velocity, &mut Transform)>) {
-    for (logo, mut transform) in &mut sprite {
-        transform.translation += logo.value * time.delta_secs();
+fn apply_velocity(
+    time: Res<Time>,
+    flow_field: Res<FlowField>,
+    mut bodies: Query<(
+        &Velocity,
+        &Transform,
+        Option<&Inertia>,
+        &FlowSusceptibility,
+        &mut LinearVelocity,
+    )>,
+) {
+    for (velocity, transform, inertia, susceptibility, mut linear_velocity) in &mut bodies {
+        let drift = flow_field.sample(transform.translation.xy()) * susceptibility.0;
+        let wanted = velocity.value.xy() + drift;
+        let Some(inertia) = inertia else {
+            linear_velocity.0 = wanted;
+            continue;
+        };
+        linear_velocity.0 = swim(linear_velocity.0, wanted, inertia, time.delta_secs());
+    }
+}
+
+// One step of an entity with inertia going from `current` toward `wanted`.
+fn swim(current: Vec2, wanted: Vec2, inertia: &Inertia, delta_secs: f32) -> Vec2 {
+    let speed = current.length();
+    let wanted_speed = wanted.length();
+    if speed <= wanted_speed {
+        return current.move_towards(wanted, inertia.acceleration * delta_secs);
     }
+
+    // Too fast, drag bleeds off the extra speed. Steering only turns toward
+    // where it wants to go, so letting go of the controls coasts to a stop.
+    let speed = wanted_speed + (speed - wanted_speed) * (-inertia.drag * delta_secs).exp();
+    let direction = current.normalize();
+    let heading = wanted.normalize_or(direction) * speed;
+    (direction * speed).move_towards(heading, inertia.acceleration * delta_secs)
 }
 
 fn update_rotation(mut sprite: Query<(&Velocity, &mut Transform), With<TurnSpeed>>) {
@@ -60,3 +139,44 @@ fn update_rotation(mut sprite: Query<(&Velocity, &mut Transform), With<TurnSpeed
         transform.rotation = rotation;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{press, run_frames, spawn_host, spawn_player, test_app};
+
+    #[test]
+    fn inertia_speeds_up_and_drag_slows_down() {
+        let inertia = Inertia::new(100., 2.);
+        let wanted = Vec2::new(50., 0.);
+
+        // Half a second to get up to speed.
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..16 {
+            velocity = swim(velocity, wanted, &inertia, 1. / 64.);
+        }
+        assert!(velocity.x > 20. && velocity.x < 30.);
+
+        // A push beyond the wanted speed fades back down to it.
+        let mut velocity = Vec2::new(250., 0.);
+        for _ in 0..64 {
+            velocity = swim(velocity, wanted, &inertia, 1. / 64.);
+        }
+        let expected = 50. + 200. * (-2f32).exp();
+        assert!((velocity.x - expected).abs() < 1.);
+    }
+
+    #[test]
+    fn hosts_block_the_player() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(60., 0.));
+        let player = spawn_player(&mut app);
+        press(&mut app, KeyCode::ArrowRight);
+        run_frames(&mut app, 120);
+
+        // Half the host plus the player's radius, the player stops at the wall.
+        let position = app.world().get::<Transform>(player).unwrap().translation;
+        assert!(position.x < 60. - 17.5 - 20. + 1.);
+        assert!(position.x > 10.);
+    }
+}
@@ -0,0 +1,344 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::arena::Arena;
+use crate::enemy::Targeting;
+use crate::host::Host;
+use crate::level::Obstacle;
+use crate::schedule::InGameSet;
+
+pub struct NavigationPlugin;
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavGrid>();
+        app.add_systems(
+            FixedUpdate,
+            (update_nav_grid, plan_paths)
+                .chain()
+                .in_set(InGameSet::UserInput),
+        );
+    }
+}
+
+const NAV_CELL_SIZE: f32 = 20.;
+// Room a virus needs to get past something without scraping along it, about
+// its radius.
+const NAV_CLEARANCE: f32 = 6.;
+// Finding a path is the expensive part, so a burst of new viruses waits its
+// turn over a few steps, heading straight for their target meanwhile.
+const PATHS_PER_STEP: usize = 16;
+// Anywhere this close to the target counts as getting there, it is blocked
+// off like any other host after all.
+const GOAL_RADIUS: f32 = 40.;
+
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// Which parts of the arena a virus can pass through, in a grid of square
+// cells. Host cells and obstacles are blocked, and so is everything outside
+// the vessel.
+#[derive(Resource, Default)]
+pub struct NavGrid {
+    origin: Vec2,
+    columns: i32,
+    rows: i32,
+    blocked: Vec<bool>,
+    // How many things were in the way when it was built.
+    blockers: usize,
+    // Bumped on every rebuild, so paths through the old grid get replanned.
+    version: u32,
+}
+
+impl NavGrid {
+    pub fn new(arena: &Arena, blockers: &[Rect]) -> Self {
+        let outline = arena.outline();
+        let min = outline.iter().copied().fold(Vec2::MAX, Vec2::min);
+        let max = outline.iter().copied().fold(Vec2::MIN, Vec2::max);
+        let size = ((max - min) / NAV_CELL_SIZE).ceil().as_ivec2();
+
+        let mut grid = NavGrid {
+            origin: min,
+            columns: size.x,
+            rows: size.y,
+            blocked: Vec::with_capacity((size.x * size.y) as usize),
+            blockers: blockers.len(),
+            version: 0,
+        };
+        for row in 0..grid.rows {
+            for column in 0..grid.columns {
+                let center = grid.center(IVec2::new(column, row));
+                let outside = arena.clamp(center, NAV_CLEARANCE) != center;
+                let in_the_way = blockers
+                    .iter()
+                    .any(|blocker| blocker.inflate(NAV_CLEARANCE).contains(center));
+                grid.blocked.push(outside || in_the_way);
+            }
+        }
+        grid
+    }
+
+    fn cell_of(&self, point: Vec2) -> IVec2 {
+        ((point - self.origin) / NAV_CELL_SIZE).floor().as_ivec2()
+    }
+
+    fn center(&self, cell: IVec2) -> Vec2 {
+        self.origin + (cell.as_vec2() + 0.5) * NAV_CELL_SIZE
+    }
+
+    fn index(&self, cell: IVec2) -> Option<usize> {
+        let inside = cell.x >= 0 && cell.y >= 0 && cell.x < self.columns && cell.y < self.rows;
+        inside.then(|| (cell.y * self.columns + cell.x) as usize)
+    }
+
+    fn is_blocked(&self, cell: IVec2) -> bool {
+        self.index(cell).is_none_or(|index| self.blocked[index])
+    }
+
+    // A* from `from` to anywhere within `GOAL_RADIUS` of `to`, as the points
+    // to head for one after the other. The last one is `to` itself.
+    pub fn find_path(&self, from: Vec2, to: Vec2) -> Option<Vec<Vec2>> {
+        let start = self.cell_of(from);
+        let start_index = self.index(start)?;
+        let goal = self.cell_of(to);
+        let reached = |cell: IVec2| self.center(cell).distance(to) <= GOAL_RADIUS;
+        // The target itself is blocked, and a virus may have been pushed a
+        // little into blocked space, so those are fine to pass through.
+        let passable = |cell: IVec2| !self.is_blocked(cell) || reached(cell) || cell == start;
+        let heuristic = |cell: IVec2| {
+            let delta = (goal - cell).abs();
+            STRAIGHT_COST * delta.max_element() as u32
+                + (DIAGONAL_COST - STRAIGHT_COST) * delta.min_element() as u32
+        };
+
+        let mut cost = vec![u32::MAX; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start_index] = 0;
+        open.push(Reverse((heuristic(start), start_index)));
+
+        while let Some(Reverse((_, index))) = open.pop() {
+            let cell = IVec2::new(index as i32 % self.columns, index as i32 / self.columns);
+            if reached(cell) {
+                return Some(self.waypoints(&came_from, index, to));
+            }
+
+            for step in NEIGHBOURS {
+                let next = cell + step;
+                if !passable(next) {
+                    continue;
+                }
+                // No cutting corners past something in the way.
+                let diagonal = step.x != 0 && step.y != 0;
+                if diagonal
+                    && (!passable(cell + IVec2::new(step.x, 0))
+                        || !passable(cell + IVec2::new(0, step.y)))
+                {
+                    continue;
+                }
+                let Some(next_index) = self.index(next) else {
+                    continue;
+                };
+                let next_cost = cost[index]
+                    + if diagonal {
+                        DIAGONAL_COST
+                    } else {
+                        STRAIGHT_COST
+                    };
+                if next_cost < cost[next_index] {
+                    cost[next_index] = next_cost;
+                    came_from[next_index] = index;
+                    open.push(Reverse((next_cost + heuristic(next), next_index)));
+                }
+            }
+        }
+        None
+    }
+
+    // Walks back from the end of a path, keeping only the cells where it
+    // turns.
+    fn waypoints(&self, came_from: &[usize], end: usize, to: Vec2) -> Vec<Vec2> {
+        let cell =
+            |index: usize| IVec2::new(index as i32 % self.columns, index as i32 / self.columns);
+        let mut waypoints = vec![to];
+        let mut index = end;
+        let mut direction = IVec2::ZERO;
+        while came_from[index] != usize::MAX {
+            let previous = came_from[index];
+            let step = cell(index) - cell(previous);
+            if step != direction {
+                waypoints.push(self.center(cell(index)));
+                direction = step;
+            }
+            index = previous;
+        }
+        waypoints.reverse();
+        waypoints
+    }
+}
+
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(1, 0),
+    IVec2::new(-1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(0, -1),
+    IVec2::new(1, 1),
+    IVec2::new(1, -1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, -1),
+];
+
+// What viruses have to find their way around.
+type Blocker = Or<(With<Host>, With<Obstacle>)>;
+type NewBlocker = Or<(Added<Host>, Added<Obstacle>)>;
+
+// The way a virus is going to its target, nearest waypoint first.
+#[derive(Component)]
+pub struct NavPath {
+    target: Entity,
+    version: u32,
+    waypoints: Vec<Vec2>,
+}
+
+impl NavPath {
+    pub fn next_waypoint(&self) -> Option<Vec2> {
+        self.waypoints.first().copied()
+    }
+}
+
+// Within a level things only ever get out of the way, as hosts die, so that
+// shows in how many are left. A new level is all new blockers, however many
+// of them there are.
+fn update_nav_grid(
+    mut grid: ResMut<NavGrid>,
+    arena: Res<Arena>,
+    colliders: Query<(Entity, &Collider, &Transform, Option<&ChildOf>)>,
+    blockers: Query<&Transform, Blocker>,
+    added: Query<(), NewBlocker>,
+) {
+    if blockers.iter().len() == grid.blockers && added.is_empty() && !arena.is_changed() {
+        return;
+    }
+
+    // Obstacles keep their colliders on a child. Transforms are composed by
+    // hand, as global ones aren't up to date yet for a level that was only
+    // just built.
+    let mut rects = Vec::new();
+    for (entity, collider, transform, child_of) in &colliders {
+        let transform = if blockers.contains(entity) {
+            *transform
+        } else if let Some(Ok(parent)) = child_of.map(|child_of| blockers.get(child_of.parent())) {
+            parent.mul_transform(*transform)
+        } else {
+            continue;
+        };
+        let aabb = collider.aabb(transform.translation.xy(), transform.rotation);
+        rects.push(Rect::from_corners(aabb.min, aabb.max));
+    }
+
+    let version = grid.version + 1;
+    *grid = NavGrid::new(&arena, &rects);
+    grid.version = version;
+}
+
+fn plan_paths(
+    mut commands: Commands,
+    grid: Res<NavGrid>,
+    mut viruses: Query<(Entity, &Transform, &Targeting, Option<&mut NavPath>)>,
+    targets: Query<&Transform>,
+) {
+    let mut planned = 0;
+    for (virus, transform, targeting, path) in &mut viruses {
+        let position = transform.translation.xy();
+        let current =
+            path.filter(|path| path.target == targeting.0 && path.version == grid.version);
+        if let Some(mut path) = current {
+            // Close enough to a waypoint to head for the next one.
+            if path.waypoints.len() > 1 && path.waypoints[0].distance(position) < NAV_CELL_SIZE {
+                path.waypoints.remove(0);
+            }
+            continue;
+        }
+
+        if planned == PATHS_PER_STEP {
+            continue;
+        }
+        let Ok(target) = targets.get(targeting.0) else {
+            continue;
+        };
+        planned += 1;
+        // Without a way through it heads straight for the target, and doesn't
+        // look again until something changes.
+        let waypoints = grid
+            .find_path(position, target.translation.xy())
+            .unwrap_or_default();
+        commands.entity(virus).insert(NavPath {
+            target: targeting.0,
+            version: grid.version,
+            waypoints,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_harness::{run_frames, spawn_host, test_app};
+
+    #[test]
+    fn paths_go_around_what_is_in_the_way() {
+        let wall = Rect::from_center_size(Vec2::new(100., 0.), Vec2::new(40., 200.));
+        let grid = NavGrid::new(&Arena::default(), &[wall]);
+        let target = Vec2::new(200., 0.);
+        let path = grid.find_path(Vec2::ZERO, target).unwrap();
+
+        assert_eq!(path.last(), Some(&target));
+        assert!(path.len() > 2);
+        // Every leg of the way stays clear of the wall.
+        let mut from = Vec2::ZERO;
+        for &waypoint in &path[..path.len() - 1] {
+            for step in 0..=20 {
+                let point = from.lerp(waypoint, step as f32 / 20.);
+                assert!(!wall.contains(point), "{point} is inside the wall");
+            }
+            from = waypoint;
+        }
+    }
+
+    #[test]
+    fn grid_is_rebuilt_when_hosts_die() {
+        let mut app = test_app();
+        let host = spawn_host(&mut app, Vec2::new(100., 0.));
+        spawn_host(&mut app, Vec2::new(-100., 0.));
+        run_frames(&mut app, 2);
+        let version = app.world().resource::<NavGrid>().version;
+        let grid = app.world().resource::<NavGrid>();
+        assert!(grid.is_blocked(grid.cell_of(Vec2::new(100., 0.))));
+
+        app.world_mut().entity_mut(host).despawn();
+        run_frames(&mut app, 1);
+        let grid = app.world().resource::<NavGrid>();
+        assert_eq!(grid.version, version + 1);
+        assert!(!grid.is_blocked(grid.cell_of(Vec2::new(100., 0.))));
+    }
+
+    #[test]
+    fn grid_is_rebuilt_for_a_new_layout_of_as_many_hosts() {
+        let mut app = test_app();
+        let old = spawn_host(&mut app, Vec2::new(100., 0.));
+        spawn_host(&mut app, Vec2::new(-100., 0.));
+        run_frames(&mut app, 2);
+        let version = app.world().resource::<NavGrid>().version;
+
+        // Like a restart that happens to build the same number of hosts.
+        app.world_mut().entity_mut(old).despawn();
+        spawn_host(&mut app, Vec2::new(0., 200.));
+        run_frames(&mut app, 1);
+        let grid = app.world().resource::<NavGrid>();
+        assert_eq!(grid.version, version + 1);
+        assert!(!grid.is_blocked(grid.cell_of(Vec2::new(100., 0.))));
+        assert!(grid.is_blocked(grid.cell_of(Vec2::new(0., 200.))));
+    }
+}
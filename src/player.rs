@@ -1,30 +1,47 @@
 use avian2d::prelude::*;
 use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
 
-use crate::enemy::{Hostile, VirusAttached};
-use crate::movement::{Speed, Velocity};
+use crate::controls::{PlayerControls, PlayerInput};
+use crate::enemy::Hostile;
+use crate::health::{DamageEvent, Died, Health, OnDeath};
+use crate::movement::{FlowSusceptibility, GameLayer, Inertia, Speed, Velocity};
 use crate::player_attack::PlayerActionParams;
 use crate::schedule::InGameSet;
+use crate::score::Score;
+use crate::spatial::FreeViruses;
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, update_velocity.in_set(InGameSet::UserInput));
+        app.add_systems(FixedUpdate, update_velocity.in_set(InGameSet::UserInput));
         app.add_systems(Update, update_camera.in_set(InGameSet::EntityUpdates));
+        app.add_observer(score_kill);
     }
 }
 
 #[derive(Component)]
 pub struct Player;
 
+#[derive(Component, Default)]
+#[require(CollisionLayers = CollisionLayers::new(
+    GameLayer::Cell,
+    [GameLayer::Default, GameLayer::Virus]
+))]
+pub struct WhiteBloodCell;
+
 #[derive(Bundle)]
 pub struct WhiteBloodCellBundle {
+    pub marker: WhiteBloodCell,
     pub sprite: Sprite,
     pub transform: Transform,
     pub velocity: Velocity,
     pub speed: Speed,
+    pub inertia: Inertia,
+    pub flow_susceptibility: FlowSusceptibility,
     pub collider: Collider,
+    pub health: Health,
     pub colliding_entities: CollidingEntities,
     pub collision_events: CollisionEventsEnabled,
 }
@@ -32,43 +49,60 @@ pub struct WhiteBloodCellBundle {
 #[derive(Bundle)]
 pub struct PlayerBundle {
     pub marker: Player,
+    pub actions: Actions<PlayerControls>,
     pub action_params: PlayerActionParams,
+    pub on_death: OnDeath,
     pub white_blood_cell_bundle: WhiteBloodCellBundle,
 }
 
 fn update_velocity(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    input: Res<PlayerInput>,
     mut players: Query<(&mut Velocity, &Speed), With<Player>>,
 ) {
     for (mut velocity, speed) in &mut players {
-        velocity.value = Vec3::ZERO;
-        if keyboard.pressed(KeyCode::ArrowLeft) {
-            velocity.value.x -= 1.0;
-        }
-        if keyboard.pressed(KeyCode::ArrowRight) {
-            velocity.value.x += 1.0;
-        }
-        if keyboard.pressed(KeyCode::ArrowDown) {
-            velocity.value.y -= 1.0;
-        }
-        if keyboard.pressed(KeyCode::ArrowUp) {
-            velocity.value.y += 1.0;
-        }
-
-        velocity.value = velocity.value.normalize_or_zero() * speed.current;
+        velocity.value = input.movement.extend(0.) * speed.current;
     }
 }
 
+// A plain hit takes away one health, a boosting player hits harder.
+const HIT_DAMAGE: f32 = 1.;
+const BOOSTED_HIT_DAMAGE: f32 = 2.;
+
 pub fn handle_virus_collision(
     trigger: Trigger<OnCollisionStart>,
-    mut commands: Commands,
-    enemies: Query<Entity, (With<Hostile>, Without<VirusAttached>)>,
+    mut damage: EventWriter<DamageEvent>,
+    enemies: Query<(), FreeViruses>,
+    boosting: Query<&PlayerActionParams>,
 ) {
-    let Ok(entity) = enemies.get(trigger.collider) else {
+    if !enemies.contains(trigger.collider) {
         return;
-    };
+    }
+
+    let boosted = boosting
+        .get(trigger.target())
+        .is_ok_and(|params| params.remaining_secs > 0.);
+    damage.write(DamageEvent {
+        target: trigger.collider,
+        amount: if boosted {
+            BOOSTED_HIT_DAMAGE
+        } else {
+            HIT_DAMAGE
+        },
+        source: Some(trigger.target()),
+    });
+}
 
-    commands.entity(entity).despawn();
+// Only enemies finished off by a white blood cell count toward the score.
+fn score_kill(
+    trigger: Trigger<Died>,
+    mut score: ResMut<Score>,
+    enemies: Query<(), With<Hostile>>,
+    cells: Query<(), With<WhiteBloodCell>>,
+) {
+    let by_a_cell = trigger.killer.is_some_and(|killer| cells.contains(killer));
+    if by_a_cell && enemies.contains(trigger.target()) {
+        score.virus_destroyed();
+    }
 }
 
 fn update_camera(
@@ -83,3 +117,66 @@ fn update_camera(
         .translation
         .smooth_nudge(&target, CAMERA_DECAY, time.delta_secs());
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archetype::BACTERIA;
+    use crate::enemy::VirusAttached;
+    use crate::health::Health;
+    use crate::test_harness::{
+        run_frames, spawn_enemy, spawn_host, spawn_player, spawn_virus, spawn_white_blood_cell,
+        test_app,
+    };
+
+    #[test]
+    fn white_blood_cell_kills_free_viruses_only() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        spawn_white_blood_cell(&mut app, Vec2::ZERO);
+        let free = spawn_virus(&mut app, Vec2::new(10., 0.), Vec2::X);
+        let attached = spawn_virus(&mut app, Vec2::new(-10., 0.), Vec2::X);
+        app.world_mut().entity_mut(attached).insert(VirusAttached);
+        run_frames(&mut app, 3);
+
+        assert!(app.world().get_entity(free).is_err());
+        assert!(app.world().get_entity(attached).is_ok());
+        assert_eq!(app.world().resource::<Score>().points, 10);
+    }
+
+    #[test]
+    fn boosting_players_hit_twice_as_hard() {
+        for (remaining_secs, damage) in [(0., HIT_DAMAGE), (1., BOOSTED_HIT_DAMAGE)] {
+            let mut app = test_app();
+            spawn_host(&mut app, Vec2::new(1000., 0.));
+            let player = spawn_player(&mut app);
+            app.world_mut()
+                .get_mut::<PlayerActionParams>(player)
+                .unwrap()
+                .remaining_secs = remaining_secs;
+            let bacterium = spawn_enemy(&mut app, BACTERIA, Vec2::new(25., 0.), Vec2::NEG_X);
+            let max = app.world().get::<Health>(bacterium).unwrap().max;
+            run_frames(&mut app, 3);
+
+            let health = app.world().get::<Health>(bacterium).unwrap().current;
+            assert_eq!(health, max - damage);
+        }
+    }
+
+    #[test]
+    fn stick_tilt_scales_player_speed() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        let mut gamepad = Gamepad::default();
+        gamepad.analog_mut().set(GamepadAxis::LeftStickX, 0.6);
+        app.world_mut().spawn(gamepad);
+        run_frames(&mut app, 2);
+
+        // Half way between the dead zone and full tilt.
+        let velocity = app.world().get::<Velocity>(player).unwrap().value;
+        let speed = app.world().get::<Speed>(player).unwrap().current;
+        assert!((velocity.x - speed / 2.).abs() < 0.01);
+        assert_eq!(velocity.y, 0.);
+    }
+}
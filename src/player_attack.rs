@@ -3,27 +3,42 @@ use bevy::color::palettes::css::*;
 use bevy::prelude::*;
 use rand::prelude::*;
 
+use crate::assets::load_image;
+use crate::controls::PlayerInput;
 use crate::enemy::{Hostile, Targeting, VirusAttached};
-use crate::movement::{Speed, Velocity};
-use crate::player::{handle_virus_collision, Player, WhiteBloodCellBundle};
+use crate::health::Health;
+use crate::movement::{FlowSusceptibility, Inertia, Speed, Velocity};
+use crate::player::{handle_virus_collision, Player, WhiteBloodCell, WhiteBloodCellBundle};
+use crate::rng::GameRng;
 use crate::schedule::InGameSet;
+use crate::spatial::{FreeViruses, SpatialIndex};
 
 pub struct PlayerAttackPlugin;
 
 impl Plugin for PlayerAttackPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerChargingGUI>();
+        app.init_resource::<DuplicationCharge>();
         app.add_systems(
-            Update,
+            FixedUpdate,
             (charge_attack, charge_multiply).in_set(InGameSet::UserInput),
         );
+        app.add_systems(FixedUpdate, player_boost.in_set(InGameSet::EntityUpdates));
         app.add_systems(
             Update,
-            (display_boost, display_multiply, player_boost).in_set(InGameSet::EntityUpdates),
+            (display_boost, display_multiply).in_set(InGameSet::EntityUpdates),
         );
         app.add_systems(
-            Update,
+            FixedUpdate,
             (select_virus, unset_nonexisting_virus, set_velocity).in_set(InGameSet::EntityUpdates),
         );
+        // Gizmos are only available when rendering.
+        app.add_systems(
+            Update,
+            draw_seeker_targets
+                .in_set(InGameSet::EntityUpdates)
+                .run_if(resource_exists::<GizmoConfigStore>),
+        );
     }
 }
 
@@ -33,6 +48,10 @@ pub struct PlayerActionParams {
     pub remaining_secs: f32,
     pub extra_seconds_per_boost_level: f32,
     pub extra_speed_per_boost_level: f32,
+    // Set while dashing toward a click, overriding the movement controls.
+    pub dash_direction: Option<Vec2>,
+    // Set when a boost fires, until its push has been given.
+    pub impulse_pending: bool,
 }
 
 #[derive(Component)]
@@ -44,6 +63,15 @@ pub struct PlayerChargingGUI {
     pub max_boost_level: f32,
 }
 
+impl Default for PlayerChargingGUI {
+    fn default() -> Self {
+        PlayerChargingGUI {
+            current_boost_level: 0.,
+            max_boost_level: 2.,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct DuplicationText;
 
@@ -51,21 +79,25 @@ const BOOSTING_BASE_SPEED: f32 = 300.;
 const BOOSTING_BASE_SECS: f32 = 0.3;
 
 fn charge_attack(
-    keyboard: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
+    input: Res<PlayerInput>,
     mut charging: ResMut<PlayerChargingGUI>,
-    mut players: Query<(&mut Velocity, &mut PlayerActionParams), With<Player>>,
+    mut players: Query<&mut PlayerActionParams, With<Player>>,
 ) {
-    if keyboard.pressed(KeyCode::Space) {
-        charging.current_boost_level += time.delta_secs();
-        charging.current_boost_level =
-            f32::min(charging.current_boost_level, charging.max_boost_level);
-    } else if keyboard.just_released(KeyCode::Space) {
-        for (_, mut params) in &mut players {
+    for mut params in &mut players {
+        if input.boost_held {
+            charging.current_boost_level += time.delta_secs();
+            charging.current_boost_level =
+                f32::min(charging.current_boost_level, charging.max_boost_level);
+        } else if input.boost_released {
+            // Both the speed and the duration grow with the charge, so does
+            // the distance covered by a dash.
             params.remaining_secs = BOOSTING_BASE_SECS
                 + params.extra_seconds_per_boost_level * charging.current_boost_level;
             params.boosted_speed = BOOSTING_BASE_SPEED
                 + params.extra_speed_per_boost_level * charging.current_boost_level;
+            params.dash_direction = input.dash_direction;
+            params.impulse_pending = true;
             charging.current_boost_level = 0.;
         }
     }
@@ -77,53 +109,67 @@ pub struct DuplicationCharge {
     pub max_progress: f32,
 }
 
+impl Default for DuplicationCharge {
+    fn default() -> Self {
+        DuplicationCharge {
+            current_progress: 0.,
+            max_progress: 0.5, //4.0,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct DuplicationBar;
 
-const CHARGE_MULTIPLY_KEYBINDING: KeyCode = KeyCode::ShiftLeft;
 fn charge_multiply(
-    keyboard: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
-    asset_server: Res<AssetServer>,
+    asset_server: Option<Res<AssetServer>>,
     mut commands: Commands,
+    input: Res<PlayerInput>,
     mut charging: ResMut<DuplicationCharge>,
     mut players: Query<(&mut Speed, &Transform), With<Player>>,
 ) {
-    if keyboard.pressed(CHARGE_MULTIPLY_KEYBINDING) {
-        charging.current_progress += time.delta_secs();
-
-        if charging.current_progress >= charging.max_progress {}
-
-        for (mut speed, transform) in &mut players {
-            if charging.current_progress < charging.max_progress {
-                // Incur some cost to duplicating oneself.
-                speed.current = speed.default / 2.0;
-                continue;
-            }
-
+    for (mut speed, transform) in &mut players {
+        if input.replicate_released {
             charging.current_progress = 0.0;
+            continue;
+        }
+        if !input.replicate_held {
+            continue;
+        }
 
-            commands
-                .spawn((
-                    WhiteBloodCellBundle {
-                        sprite: Sprite {
-                            image: asset_server.load("white_blood_cell.png"),
-                            custom_size: Some(Vec2::splat(40.0)),
-                            ..default()
-                        },
-                        transform: Transform::from_translation(transform.translation),
-                        velocity: Velocity::new(Vec3::ZERO),
-                        speed: Speed::new(25.0),
-                        collider: Collider::circle(20.0),
-                        colliding_entities: CollidingEntities::default(),
-                        collision_events: CollisionEventsEnabled,
-                    },
-                    SeekVirus,
-                ))
-                .observe(handle_virus_collision);
+        charging.current_progress += time.delta_secs();
+        if charging.current_progress < charging.max_progress {
+            // Incur some cost to duplicating oneself.
+            speed.current = speed.default / 2.0;
+            continue;
         }
-    } else if keyboard.just_released(CHARGE_MULTIPLY_KEYBINDING) {
+
         charging.current_progress = 0.0;
+
+        commands
+            .spawn((
+                WhiteBloodCellBundle {
+                    marker: WhiteBloodCell,
+                    sprite: Sprite {
+                        image: load_image(asset_server.as_deref(), "white_blood_cell.png"),
+                        custom_size: Some(Vec2::splat(40.0)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(transform.translation),
+                    velocity: Velocity::new(Vec3::ZERO),
+                    speed: Speed::new(25.0),
+                    // Clones drift lazily after their targets.
+                    inertia: Inertia::new(50.0, 1.0),
+                    flow_susceptibility: FlowSusceptibility(0.6),
+                    collider: Collider::circle(20.0),
+                    health: Health::new(2.0).with_grace(0.5),
+                    colliding_entities: CollidingEntities::default(),
+                    collision_events: CollisionEventsEnabled,
+                },
+                SeekVirus,
+            ))
+            .observe(handle_virus_collision);
     }
 }
 
@@ -137,16 +183,38 @@ fn display_multiply(
     }
 }
 
+// A boost is a single push on top of the player's motion, which drag then
+// bleeds off again.
 fn player_boost(
     time: Res<Time>,
-    mut players: Query<(&mut Speed, &mut PlayerActionParams), With<Player>>,
+    mut players: Query<
+        (
+            &Speed,
+            &mut Velocity,
+            &mut LinearVelocity,
+            &mut PlayerActionParams,
+        ),
+        With<Player>,
+    >,
 ) {
-    for (mut speed, mut action_params) in &mut players {
-        if action_params.remaining_secs < 0. {
-            speed.current = speed.default;
+    for (speed, mut velocity, mut linear_velocity, mut action_params) in &mut players {
+        if action_params.remaining_secs <= 0. {
+            action_params.dash_direction = None;
             continue;
         }
-        speed.current = action_params.boosted_speed;
+        // A dash toward a click ignores where the player is steering.
+        if let Some(direction) = action_params.dash_direction {
+            velocity.value = direction.extend(0.) * speed.current;
+        }
+        if action_params.impulse_pending {
+            action_params.impulse_pending = false;
+            // Without steering, the push goes the way the cell is drifting, or up.
+            let direction = velocity
+                .value
+                .xy()
+                .normalize_or(linear_velocity.normalize_or(Vec2::Y));
+            linear_velocity.0 += direction * action_params.boosted_speed;
+        }
         action_params.remaining_secs -= time.delta_secs();
     }
 }
@@ -166,39 +234,27 @@ fn display_boost(
     }
 }
 
+// Seekers go after any virus they can see, or else one of the few closest.
+pub const SEEKER_SIGHT: f32 = 300.;
+pub const SEEKER_CANDIDATES: usize = 5;
+
 fn select_virus(
     mut commands: Commands,
+    mut rng: ResMut<GameRng>,
+    viruses: Res<SpatialIndex<FreeViruses>>,
     seekers: Query<(Entity, &Transform), (With<SeekVirus>, Without<Targeting>)>,
-    targets: Query<(Entity, &Transform), With<Hostile>>,
 ) {
-    let mut targets_list: Vec<(Entity, &Transform)> = targets.iter().collect();
-    if targets_list.is_empty() {
-        return;
-    }
-
-    let mut rng = rand::rng();
-
     for (seeker, seeker_transform) in seekers {
-        let mut min_dist = f32::MAX;
-        let mut closest_target: Option<&Entity> = None;
-
-        targets_list.shuffle(&mut rng);
-
-        // Use the stopping rule to pseudo-randomly pick a target
-        // that's relatively close.
-        for (i, (candidate, transform)) in targets_list.iter().enumerate() {
-            let dist = seeker_transform.translation.distance(transform.translation);
-            if dist < min_dist {
-                if (i as f32 / targets_list.len() as f32) < 0.37 {
-                    min_dist = dist;
-                } else {
-                    closest_target = Some(candidate);
-                }
-            }
+        let position = seeker_transform.translation.xy();
+        let mut candidates = viruses.within_radius(position, SEEKER_SIGHT);
+        if candidates.is_empty() {
+            candidates = viruses.nearest(position, SEEKER_CANDIDATES);
         }
 
-        if let Some(entity) = closest_target {
-            commands.entity(seeker).insert(Targeting(*entity));
+        // Pseudo-randomly pick a target that's relatively close, so clones
+        // spread out over a swarm.
+        if let Some(&(entity, _)) = candidates.choose(&mut **rng) {
+            commands.entity(seeker).insert(Targeting(entity));
         };
     }
 }
@@ -219,7 +275,6 @@ fn unset_nonexisting_virus(
 const TARGET_DEBUG_COLOR: Srgba = GREEN;
 
 fn set_velocity(
-    mut gizmos: Gizmos,
     mut seekers: Query<
         (&mut Velocity, &Transform, &Targeting, &Speed),
         (With<SeekVirus>, Without<Player>),
@@ -230,24 +285,114 @@ fn set_velocity(
         // Return to the center in between waves.
         let Ok(target_transform) = targets.get(target.0) else {
             let to_center = -seeker.translation;
-
-            // DO NOT RELEASE
-            gizmos.line_2d(seeker.translation.xy(), Vec2::ZERO, TARGET_DEBUG_COLOR);
-
             let direction = -to_center.normalize_or_zero();
             velocity.value = direction * speed.current;
             continue;
         };
 
         let to_target = target_transform.translation.xy() - seeker.translation.xy();
+        velocity.value = to_target.normalize_or_zero().extend(0.0) * speed.current;
+    }
+}
 
-        // DO NOT RELEASE
-        gizmos.line_2d(
-            seeker.translation.xy(),
-            target_transform.translation.xy(),
-            TARGET_DEBUG_COLOR,
+// DO NOT RELEASE
+fn draw_seeker_targets(
+    mut gizmos: Gizmos,
+    seekers: Query<(&Transform, &Targeting), With<SeekVirus>>,
+    targets: Query<&Transform, Without<SeekVirus>>,
+) {
+    for (seeker, target) in &seekers {
+        let target_position = targets
+            .get(target.0)
+            .map_or(Vec2::ZERO, |transform| transform.translation.xy());
+        gizmos.line_2d(seeker.translation.xy(), target_position, TARGET_DEBUG_COLOR);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controls::{MouseCursor, MouseSteering, TouchButtonState, TouchControls};
+    use crate::test_harness::{press, release, run_frames, spawn_host, spawn_player, test_app};
+
+    #[test]
+    fn boost_charges_while_held_and_fires_on_release() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        run_frames(&mut app, 1);
+
+        press(&mut app, KeyCode::Space);
+        run_frames(&mut app, 30);
+        let charged = app
+            .world()
+            .resource::<PlayerChargingGUI>()
+            .current_boost_level;
+        assert!(charged > 0.4);
+
+        release(&mut app, KeyCode::Space);
+        run_frames(&mut app, 1);
+        assert_eq!(
+            app.world()
+                .resource::<PlayerChargingGUI>()
+                .current_boost_level,
+            0.
         );
+        let params = app.world().get::<PlayerActionParams>(player).unwrap();
+        assert!(params.boosted_speed > BOOSTING_BASE_SPEED);
+    }
 
-        velocity.value = to_target.normalize_or_zero().extend(0.0) * speed.current;
+    #[test]
+    fn touch_boost_button_charges_like_the_key() {
+        let mut app = test_app();
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        run_frames(&mut app, 1);
+
+        app.world_mut().resource_mut::<TouchControls>().boost.held = true;
+        run_frames(&mut app, 30);
+        assert!(
+            app.world()
+                .resource::<PlayerChargingGUI>()
+                .current_boost_level
+                > 0.4
+        );
+
+        app.world_mut().resource_mut::<TouchControls>().boost = TouchButtonState {
+            held: false,
+            released: true,
+        };
+        run_frames(&mut app, 1);
+        let params = app.world().get::<PlayerActionParams>(player).unwrap();
+        assert!(params.boosted_speed > BOOSTING_BASE_SPEED);
+    }
+
+    #[test]
+    fn mouse_dash_heads_for_the_release_point() {
+        let mut app = test_app();
+        app.insert_resource(MouseSteering(true));
+        app.insert_resource(MouseCursor(Some(Vec2::new(0., 500.))));
+        spawn_host(&mut app, Vec2::new(1000., 0.));
+        let player = spawn_player(&mut app);
+        run_frames(&mut app, 1);
+
+        press(&mut app, MouseButton::Left);
+        run_frames(&mut app, 30);
+        assert!(
+            app.world()
+                .resource::<PlayerChargingGUI>()
+                .current_boost_level
+                > 0.4
+        );
+        release(&mut app, MouseButton::Left);
+        run_frames(&mut app, 1);
+
+        // Moving the cursor away doesn't turn the dash.
+        app.insert_resource(MouseCursor(Some(Vec2::new(500., 0.))));
+        run_frames(&mut app, 1);
+        let velocity = app.world().get::<LinearVelocity>(player).unwrap().0;
+        // Only the bloodstream turns it a little to the side.
+        assert!(velocity.x.abs() < velocity.y * 0.05);
+        assert!(velocity.y > BOOSTING_BASE_SPEED);
     }
 }
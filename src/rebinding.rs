@@ -0,0 +1,179 @@
+use bevy::prelude::*;
+use bevy_enhanced_input::prelude::*;
+
+use crate::controls::{key_label, KeyBindings, MouseSteering, RebindableKey};
+use crate::menu::menu_screen;
+use crate::schedule::GameState;
+use crate::storage;
+
+pub struct RebindingPlugin;
+
+impl Plugin for RebindingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RebindingMenu>();
+        app.add_systems(Startup, load_key_bindings);
+        app.add_systems(OnEnter(GameState::Controls), spawn_rebinding_screen);
+        app.add_systems(
+            Update,
+            (
+                open_rebinding_menu
+                    .run_if(in_state(GameState::MainMenu).or(in_state(GameState::Paused))),
+                (handle_rebinding_input, update_rebinding_screen)
+                    .chain()
+                    .run_if(in_state(GameState::Controls)),
+            ),
+        );
+    }
+}
+
+const OPEN_KEYBINDING: KeyCode = KeyCode::KeyC;
+const BACK_KEYBINDING: KeyCode = KeyCode::Escape;
+const SELECT_KEYBINDING: KeyCode = KeyCode::Enter;
+const MOUSE_STEERING_KEYBINDING: KeyCode = KeyCode::KeyM;
+const SETTINGS_KEY: &str = "qualified_immunity_settings.txt";
+
+#[derive(Resource)]
+struct RebindingMenu {
+    // The menu to go back to when leaving the screen.
+    return_to: GameState,
+    selected: usize,
+    // Whether the next key press becomes the binding of the selected action.
+    capturing: bool,
+    message: String,
+}
+
+impl Default for RebindingMenu {
+    fn default() -> Self {
+        RebindingMenu {
+            return_to: GameState::MainMenu,
+            selected: 0,
+            capturing: false,
+            message: String::new(),
+        }
+    }
+}
+
+#[derive(Component)]
+enum RebindingText {
+    Binding(usize),
+    MouseSteering,
+    Message,
+}
+
+fn load_key_bindings(mut commands: Commands) {
+    if let Some(contents) = storage::load(SETTINGS_KEY) {
+        commands.insert_resource(KeyBindings::parse(&contents));
+        commands.insert_resource(MouseSteering::parse(&contents));
+    }
+}
+
+fn save_settings(keys: &KeyBindings, steering: MouseSteering) {
+    storage::save(
+        SETTINGS_KEY,
+        &format!("{}{}", keys.serialize(), steering.serialize()),
+    );
+}
+
+fn open_rebinding_menu(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut menu: ResMut<RebindingMenu>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard.just_pressed(OPEN_KEYBINDING) {
+        *menu = RebindingMenu {
+            return_to: *state.get(),
+            ..default()
+        };
+        next_state.set(GameState::Controls);
+    }
+}
+
+fn spawn_rebinding_screen(mut commands: Commands) {
+    commands
+        .spawn(menu_screen(GameState::Controls))
+        .with_children(|screen| {
+            screen.spawn((Text::new("Controls"), TextFont::from_font_size(48.0)));
+            for index in 0..RebindableKey::ALL.len() {
+                screen.spawn((Text::default(), RebindingText::Binding(index)));
+            }
+            screen.spawn((Text::default(), RebindingText::MouseSteering));
+            screen.spawn((Text::default(), RebindingText::Message));
+            screen.spawn(Text::new(
+                "Up/Down to choose, Enter to change, M to toggle mouse steering, Escape to go back",
+            ));
+        });
+}
+
+fn handle_rebinding_input(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut menu: ResMut<RebindingMenu>,
+    mut keys: ResMut<KeyBindings>,
+    mut steering: ResMut<MouseSteering>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if menu.capturing {
+        let Some(&key) = keyboard.get_just_pressed().next() else {
+            return;
+        };
+        menu.capturing = false;
+
+        let action = RebindableKey::ALL[menu.selected];
+        if key == BACK_KEYBINDING {
+            menu.message.clear();
+        } else if let Some(other) = keys.action_for(key).filter(|&other| other != action) {
+            menu.message = format!("{} is already used for {}", key_label(key), other.label());
+        } else {
+            keys.set(action, key);
+            save_settings(&keys, *steering);
+            commands.trigger(RebuildBindings);
+            menu.message.clear();
+        }
+        return;
+    }
+
+    if keyboard.just_pressed(BACK_KEYBINDING) {
+        next_state.set(menu.return_to);
+    } else if keyboard.just_pressed(SELECT_KEYBINDING) {
+        menu.capturing = true;
+        menu.message = format!(
+            "Press a key for {}, or Escape to cancel",
+            RebindableKey::ALL[menu.selected].label()
+        );
+    } else if keyboard.just_pressed(MOUSE_STEERING_KEYBINDING) {
+        steering.0 = !steering.0;
+        save_settings(&keys, *steering);
+        commands.trigger(RebuildBindings);
+    } else if keyboard.just_pressed(KeyCode::ArrowUp) {
+        menu.selected = menu.selected.saturating_sub(1);
+    } else if keyboard.just_pressed(KeyCode::ArrowDown) {
+        menu.selected = (menu.selected + 1).min(RebindableKey::ALL.len() - 1);
+    }
+}
+
+fn update_rebinding_screen(
+    menu: Res<RebindingMenu>,
+    keys: Res<KeyBindings>,
+    steering: Res<MouseSteering>,
+    mut texts: Query<(&mut Text, &RebindingText)>,
+) {
+    for (mut text, kind) in &mut texts {
+        text.0 = match *kind {
+            RebindingText::Binding(index) => {
+                let action = RebindableKey::ALL[index];
+                let cursor = if index == menu.selected { "> " } else { "  " };
+                format!(
+                    "{}{}: {}",
+                    cursor,
+                    action.label(),
+                    key_label(keys.get(action))
+                )
+            }
+            RebindingText::MouseSteering => {
+                format!("Mouse steering: {}", if steering.0 { "on" } else { "off" })
+            }
+            RebindingText::Message => menu.message.clone(),
+        };
+    }
+}
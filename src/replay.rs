@@ -0,0 +1,301 @@
+use bevy::prelude::*;
+
+use crate::controls::PlayerInput;
+use crate::level::EnemySpawner;
+use crate::rng::{reseed_rng, GameRng};
+use crate::schedule::{GameState, InGameSet};
+use crate::storage;
+
+// Records the player's input every gameplay step of a run with
+// `--record <file>`, and plays a recording back with `--replay <file>`. With
+// `record` off nothing is written, so headless runs never record.
+pub struct ReplayPlugin {
+    pub record: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        start_replay(app);
+        app.add_systems(
+            FixedUpdate,
+            play_back_input
+                .before(InGameSet::UserInput)
+                .run_if(in_state(GameState::Playing).and(resource_exists::<Replay>)),
+        );
+
+        // Playing back isn't worth recording.
+        if !self.record || app.world().contains_resource::<Replay>() {
+            return;
+        }
+        let Some(path) = path_from_args("--record") else {
+            return;
+        };
+        app.insert_resource(RecordTo(path))
+            .init_resource::<Recording>();
+        app.add_systems(
+            OnEnter(GameState::Playing),
+            start_recording
+                .after(reseed_rng)
+                .run_if(not(any_with_component::<EnemySpawner>)),
+        );
+        app.add_systems(FixedUpdate, record_input.in_set(InGameSet::UserInput));
+        app.add_systems(OnExit(GameState::Playing), save_recording);
+    }
+}
+
+const REPLAY_HEADER: &str = "qualified_immunity replay 2";
+
+const BOOST_HELD: u8 = 1;
+const BOOST_RELEASED: u8 = 2;
+const REPLICATE_HELD: u8 = 4;
+const REPLICATE_RELEASED: u8 = 8;
+const DASH: u8 = 16;
+
+// "move_x move_y flags [dash_x dash_y]". Floats are written in full so
+// playback sees the exact same values.
+fn serialize_input(input: &PlayerInput) -> String {
+    let flags = [
+        (input.boost_held, BOOST_HELD),
+        (input.boost_released, BOOST_RELEASED),
+        (input.replicate_held, REPLICATE_HELD),
+        (input.replicate_released, REPLICATE_RELEASED),
+        (input.dash_direction.is_some(), DASH),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .fold(0, |flags, (_, flag)| flags | flag);
+
+    let mut line = format!("{} {} {}", input.movement.x, input.movement.y, flags);
+    if let Some(dash) = input.dash_direction {
+        line += &format!(" {} {}", dash.x, dash.y);
+    }
+    line
+}
+
+fn parse_input<'a>(mut fields: impl Iterator<Item = &'a str>) -> Option<PlayerInput> {
+    let movement = Vec2::new(fields.next()?.parse().ok()?, fields.next()?.parse().ok()?);
+    let flags: u8 = fields.next()?.parse().ok()?;
+    let dash_direction = if flags & DASH != 0 {
+        Some(Vec2::new(
+            fields.next()?.parse().ok()?,
+            fields.next()?.parse().ok()?,
+        ))
+    } else {
+        None
+    };
+
+    Some(PlayerInput {
+        movement,
+        boost_held: flags & BOOST_HELD != 0,
+        boost_released: flags & BOOST_RELEASED != 0,
+        replicate_held: flags & REPLICATE_HELD != 0,
+        replicate_released: flags & REPLICATE_RELEASED != 0,
+        dash_direction,
+    })
+}
+
+// Gameplay runs in fixed steps, so the input of each step and the seed are all
+// it takes to play a run out the same way again.
+#[derive(Resource, Default)]
+struct Recording {
+    seed: u64,
+    steps: Vec<PlayerInput>,
+}
+
+impl Recording {
+    // The header and seed, then one line per run of identical steps, led by
+    // how many times it repeats.
+    fn serialize(&self) -> String {
+        let mut contents = format!("{REPLAY_HEADER}\nseed {}\n", self.seed);
+        for run in self.steps.chunk_by(|a, b| a == b) {
+            contents += &format!("{} {}\n", run.len(), serialize_input(&run[0]));
+        }
+        contents
+    }
+
+    fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        if lines.next()? != REPLAY_HEADER {
+            return None;
+        }
+        let seed = lines.next()?.strip_prefix("seed ")?.parse().ok()?;
+
+        let mut steps = Vec::new();
+        for line in lines {
+            let mut fields = line.split_whitespace();
+            let repeats: usize = fields.next()?.parse().ok()?;
+            let input = parse_input(fields)?;
+            steps.extend(std::iter::repeat_n(input, repeats));
+        }
+        Some(Recording { seed, steps })
+    }
+}
+
+// Present while a recording is being played back.
+#[derive(Resource)]
+struct Replay {
+    steps: Vec<PlayerInput>,
+    next: usize,
+}
+
+// Where to save the recording to.
+#[derive(Resource)]
+struct RecordTo(String);
+
+fn path_from_args(flag: &str) -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == flag {
+            let path = args.next();
+            if path.is_none() {
+                warn!("{} expects the path of a replay file", flag);
+            }
+            return path;
+        }
+    }
+    None
+}
+
+// Done while building the app so the first run already uses the recorded seed.
+fn start_replay(app: &mut App) {
+    let Some(path) = path_from_args("--replay") else {
+        return;
+    };
+    let Some(recording) = storage::load(&path).and_then(|contents| Recording::parse(&contents))
+    else {
+        warn!("Could not read replay {}", path);
+        return;
+    };
+    info!(
+        "Playing back {} steps of seed {} from {}",
+        recording.steps.len(),
+        recording.seed,
+        path
+    );
+    app.insert_resource(GameRng::from_seed(recording.seed))
+        .insert_resource(Replay {
+            steps: recording.steps,
+            next: 0,
+        });
+    app.world_mut()
+        .resource_mut::<NextState<GameState>>()
+        .set(GameState::Playing);
+}
+
+fn play_back_input(
+    mut commands: Commands,
+    mut replay: ResMut<Replay>,
+    mut input: ResMut<PlayerInput>,
+) {
+    let Some(&step) = replay.steps.get(replay.next) else {
+        info!("Replay finished, handing control back to the player");
+        commands.remove_resource::<Replay>();
+        return;
+    };
+    *input = step;
+    replay.next += 1;
+}
+
+fn start_recording(rng: Res<GameRng>, mut recording: ResMut<Recording>) {
+    *recording = Recording {
+        seed: rng.seed(),
+        steps: Vec::new(),
+    };
+}
+
+fn record_input(input: Res<PlayerInput>, mut recording: ResMut<Recording>) {
+    recording.steps.push(*input);
+}
+
+// Also saved when pausing, so a replay can be grabbed mid-run.
+fn save_recording(recording: Res<Recording>, path: Res<RecordTo>) {
+    storage::save(&path.0, &recording.serialize());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headless::gameplay_app;
+    use crate::score::Score;
+
+    const STEPS: usize = 1200;
+
+    // Circles around, dashing and replicating every so often.
+    fn scripted_steps() -> Vec<PlayerInput> {
+        (0..STEPS)
+            .map(|step| PlayerInput {
+                movement: Vec2::from_angle(step as f32 / 100.),
+                boost_held: step % 200 < 40,
+                boost_released: step % 200 == 40,
+                replicate_held: step % 300 >= 200 && step % 300 < 280,
+                replicate_released: step % 300 == 280,
+                dash_direction: None,
+            })
+            .collect()
+    }
+
+    // The wave, score and where everything is once the replay has played out.
+    fn play_out(seed: u64) -> (i32, u32, Vec<(Entity, Vec3)>) {
+        // Like a simulation, without logging all over the test output.
+        let mut app = gameplay_app();
+        app.add_plugins(ReplayPlugin { record: false })
+            .insert_resource(GameRng::from_seed(seed))
+            .insert_resource(Replay {
+                steps: scripted_steps(),
+                next: 0,
+            });
+        app.finish();
+        app.cleanup();
+        for _ in 0..STEPS {
+            app.update();
+        }
+
+        let wave = app
+            .world_mut()
+            .query::<&EnemySpawner>()
+            .single(app.world())
+            .unwrap()
+            .wave;
+        let score = app.world().resource::<Score>().points;
+        let mut positions: Vec<(Entity, Vec3)> = app
+            .world_mut()
+            .query::<(Entity, &Transform)>()
+            .iter(app.world())
+            .map(|(entity, transform)| (entity, transform.translation))
+            .collect();
+        positions.sort_by_key(|(entity, _)| *entity);
+        (wave, score, positions)
+    }
+
+    #[test]
+    fn replays_play_out_the_same_every_time() {
+        let first = play_out(3);
+        assert!(first.2.len() > 1);
+        assert_eq!(first, play_out(3));
+        // And the seed actually matters.
+        assert_ne!(first, play_out(4));
+    }
+
+    #[test]
+    fn recording_survives_a_save_and_load() {
+        let idle = PlayerInput::default();
+        let dash = PlayerInput {
+            movement: Vec2::new(0.70710677, -0.1),
+            boost_released: true,
+            dash_direction: Some(Vec2::new(0.6, 0.8)),
+            ..default()
+        };
+        let recording = Recording {
+            seed: 42,
+            steps: vec![idle, idle, idle, dash, idle],
+        };
+
+        let contents = recording.serialize();
+        // Identical steps share a line.
+        assert_eq!(contents.lines().count(), 5);
+
+        let loaded = Recording::parse(&contents).unwrap();
+        assert_eq!(loaded.seed, 42);
+        assert_eq!(loaded.steps, recording.steps);
+    }
+}
@@ -0,0 +1,69 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(seed_from_args().map_or_else(GameRng::random, GameRng::from_seed));
+    }
+}
+
+// Every gameplay system draws from this so that a run can be reproduced from
+// its seed. Pass `--seed <n>` to pin it, otherwise each run gets a new one.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng {
+    seed: u64,
+    fixed_seed: bool,
+    #[deref]
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn from_seed(seed: u64) -> Self {
+        GameRng {
+            seed,
+            fixed_seed: true,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn random() -> Self {
+        let seed = rand::random();
+        GameRng {
+            seed,
+            fixed_seed: false,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn start_run(&mut self) {
+        if !self.fixed_seed {
+            self.seed = rand::random();
+        }
+        self.rng = StdRng::seed_from_u64(self.seed);
+        info!("Starting run with seed {}", self.seed);
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let Some(seed) = args.next().and_then(|seed| seed.parse().ok()) else {
+                warn!("--seed expects an unsigned integer, using a random seed");
+                return None;
+            };
+            return Some(seed);
+        }
+    }
+    None
+}
+
+pub fn reseed_rng(mut rng: ResMut<GameRng>) {
+    rng.start_run();
+}
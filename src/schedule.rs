@@ -1,10 +1,24 @@
-use bevy::ecs::schedule::ApplyDeferred;
+use bevy::ecs::schedule::{ApplyDeferred, ScheduleLabel};
 use bevy::prelude::*;
 
+#[derive(States, Debug, Hash, PartialEq, Eq, Clone, Copy, Default)]
+#[states(scoped_entities)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    Playing,
+    Paused,
+    GameOver,
+    // Rebinding keys, opened from the main or pause menu.
+    Controls,
+}
+
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
 pub enum InGameSet {
     UserInput,
     EntityUpdates,
+    // Moving everything by the velocities worked out in `EntityUpdates`.
+    Physics,
     CollisionDetection,
     DespawnEntities,
 }
@@ -13,16 +27,23 @@ pub struct SchedulePlugin;
 
 impl Plugin for SchedulePlugin {
     fn build(&self, app: &mut App) {
-        app.configure_sets(
-            Update,
-            (
-                InGameSet::UserInput,
-                InGameSet::EntityUpdates,
-                InGameSet::CollisionDetection,
-                InGameSet::DespawnEntities,
+        app.init_state::<GameState>();
+        // Gameplay steps in `FixedUpdate` so it plays out the same at any frame
+        // rate. Presentation, like the HUD and camera, follows along in `Update`.
+        for schedule in [FixedUpdate.intern(), Update.intern()] {
+            app.configure_sets(
+                schedule,
+                (
+                    InGameSet::UserInput,
+                    InGameSet::EntityUpdates,
+                    InGameSet::Physics,
+                    InGameSet::CollisionDetection,
+                    InGameSet::DespawnEntities,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
             )
-                .chain(),
-        )
-        .add_systems(Update, ApplyDeferred.before(InGameSet::EntityUpdates));
+            .add_systems(schedule, ApplyDeferred.before(InGameSet::EntityUpdates));
+        }
     }
 }
@@ -0,0 +1,178 @@
+use std::cmp::Reverse;
+
+use bevy::prelude::*;
+
+use crate::level::EnemySpawner;
+use crate::schedule::GameState;
+use crate::storage;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>();
+        app.add_systems(Startup, load_high_scores);
+        app.add_systems(OnEnter(GameState::GameOver), record_high_score);
+    }
+}
+
+const POINTS_PER_VIRUS: u32 = 10;
+const POINTS_PER_WAVE: u32 = 100;
+const POINTS_PER_HOST: u32 = 20;
+
+const MAX_HIGH_SCORES: usize = 10;
+const HIGH_SCORES_KEY: &str = "qualified_immunity_high_scores.txt";
+
+#[derive(Resource, Default)]
+pub struct Score {
+    pub points: u32,
+}
+
+impl Score {
+    pub fn virus_destroyed(&mut self) {
+        self.points += POINTS_PER_VIRUS;
+    }
+
+    // Surviving a wave is worth more the more tissue is still standing.
+    pub fn wave_survived(&mut self, hosts_alive: usize) {
+        self.points += POINTS_PER_WAVE + POINTS_PER_HOST * hosts_alive as u32;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HighScore {
+    pub points: u32,
+    pub wave: i32,
+}
+
+// Best runs first.
+#[derive(Resource, Default)]
+pub struct HighScores(pub Vec<HighScore>);
+
+impl HighScores {
+    fn parse(contents: &str) -> Self {
+        let mut scores: Vec<HighScore> = contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some(HighScore {
+                    points: fields.next()?.parse().ok()?,
+                    wave: fields.next()?.parse().ok()?,
+                })
+            })
+            .collect();
+        scores.sort_by_key(|score| Reverse(score.points));
+        scores.truncate(MAX_HIGH_SCORES);
+        HighScores(scores)
+    }
+
+    fn serialize(&self) -> String {
+        self.0
+            .iter()
+            .map(|score| format!("{} {}\n", score.points, score.wave))
+            .collect()
+    }
+
+    // Returns whether the score made it onto the table.
+    fn insert(&mut self, score: HighScore) -> bool {
+        let position = self.0.partition_point(|other| other.points >= score.points);
+        if position >= MAX_HIGH_SCORES {
+            return false;
+        }
+
+        self.0.insert(position, score);
+        self.0.truncate(MAX_HIGH_SCORES);
+        true
+    }
+}
+
+fn load_high_scores(mut commands: Commands) {
+    let high_scores = storage::load(HIGH_SCORES_KEY)
+        .map(|contents| HighScores::parse(&contents))
+        .unwrap_or_default();
+    commands.insert_resource(high_scores);
+}
+
+pub fn record_high_score(
+    score: Res<Score>,
+    spawner: Single<&EnemySpawner>,
+    mut high_scores: ResMut<HighScores>,
+) {
+    let new_score = HighScore {
+        points: score.points,
+        wave: spawner.wave - 1,
+    };
+
+    if high_scores.insert(new_score) {
+        storage::save(HIGH_SCORES_KEY, &high_scores.serialize());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points(high_scores: &HighScores) -> Vec<u32> {
+        high_scores.0.iter().map(|score| score.points).collect()
+    }
+
+    #[test]
+    fn high_scores_survive_a_save_and_load() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(HighScore {
+            points: 300,
+            wave: 2,
+        });
+        high_scores.insert(HighScore {
+            points: 950,
+            wave: 6,
+        });
+
+        let loaded = HighScores::parse(&high_scores.serialize());
+        assert_eq!(loaded.0, high_scores.0);
+
+        // Garbage lines are skipped, and whatever is left is put in order.
+        let loaded = HighScores::parse("120 1\nnot a score\n40\n800 4\n");
+        assert_eq!(
+            loaded.0,
+            vec![
+                HighScore {
+                    points: 800,
+                    wave: 4
+                },
+                HighScore {
+                    points: 120,
+                    wave: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_best_scores_are_kept() {
+        let mut high_scores = HighScores::default();
+        for points in (1..=MAX_HIGH_SCORES as u32).map(|i| i * 100) {
+            assert!(high_scores.insert(HighScore { points, wave: 1 }));
+        }
+        assert_eq!(points(&high_scores)[0], MAX_HIGH_SCORES as u32 * 100);
+
+        // Too low to make the table.
+        assert!(!high_scores.insert(HighScore {
+            points: 50,
+            wave: 1
+        }));
+        assert_eq!(high_scores.0.len(), MAX_HIGH_SCORES);
+
+        // Slots in behind the scores it beats, pushing the lowest out.
+        assert!(high_scores.insert(HighScore {
+            points: 250,
+            wave: 2
+        }));
+        let expected: Vec<u32> = vec![1000, 900, 800, 700, 600, 500, 400, 300, 250, 200];
+        assert_eq!(points(&high_scores), expected);
+
+        // A file with more than fits is cut down to size.
+        let loaded = HighScores::parse(&format!("{}10 1\n", high_scores.serialize()));
+        assert_eq!(points(&loaded), expected);
+    }
+}
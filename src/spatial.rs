@@ -0,0 +1,220 @@
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::enemy::{Hostile, VirusAttached};
+use crate::host::Host;
+use crate::schedule::InGameSet;
+
+pub struct SpatialPlugin;
+
+impl Plugin for SpatialPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpatialIndex<FreeViruses>>();
+        app.init_resource::<SpatialIndex<Hosts>>();
+        // Targeting happens in `EntityUpdates`, by then everything is in place.
+        app.add_systems(
+            FixedUpdate,
+            (update_index::<FreeViruses>, update_index::<Hosts>).in_set(InGameSet::UserInput),
+        );
+    }
+}
+
+// Viruses still roaming about. Attached ones are part of their host.
+pub type FreeViruses = (With<Hostile>, Without<VirusAttached>);
+pub type Hosts = (With<Host>, Without<Hostile>);
+
+// Big enough that most lookups only touch a handful of cells.
+const SPATIAL_CELL_SIZE: f32 = 100.;
+
+// Where every entity matching `F` was at the start of this step, bucketed by
+// grid cell so looking around a point only visits the cells near it.
+#[derive(Resource)]
+pub struct SpatialIndex<F> {
+    cells: HashMap<IVec2, Vec<(Entity, Vec2)>>,
+    // The smallest and largest cell with anything in it.
+    min: IVec2,
+    max: IVec2,
+    _filter: PhantomData<F>,
+}
+
+impl<F> Default for SpatialIndex<F> {
+    fn default() -> Self {
+        SpatialIndex {
+            cells: HashMap::new(),
+            min: IVec2::MAX,
+            max: IVec2::MIN,
+            _filter: PhantomData,
+        }
+    }
+}
+
+fn cell_of(point: Vec2) -> IVec2 {
+    (point / SPATIAL_CELL_SIZE).floor().as_ivec2()
+}
+
+impl<F> SpatialIndex<F> {
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.min = IVec2::MAX;
+        self.max = IVec2::MIN;
+    }
+
+    pub fn insert(&mut self, entity: Entity, position: Vec2) {
+        let cell = cell_of(position);
+        self.min = self.min.min(cell);
+        self.max = self.max.max(cell);
+        self.cells.entry(cell).or_default().push((entity, position));
+    }
+
+    fn cell(&self, cell: IVec2) -> &[(Entity, Vec2)] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn within_radius(&self, point: Vec2, radius: f32) -> Vec<(Entity, Vec2)> {
+        let (from, to) = (cell_of(point - radius), cell_of(point + radius));
+        let from = from.max(self.min);
+        let to = to.min(self.max);
+
+        let mut found = Vec::new();
+        for y in from.y..=to.y {
+            for x in from.x..=to.x {
+                found.extend(
+                    self.cell(IVec2::new(x, y)).iter().filter(|(_, position)| {
+                        position.distance_squared(point) <= radius * radius
+                    }),
+                );
+            }
+        }
+        found
+    }
+
+    // The `k` entities closest to `point`, closest first.
+    pub fn nearest(&self, point: Vec2, k: usize) -> Vec<(Entity, Vec2)> {
+        let mut found: Vec<(f32, Entity, Vec2)> = Vec::new();
+        if k == 0 || self.cells.is_empty() {
+            return Vec::new();
+        }
+
+        let center = cell_of(point);
+        let farthest_ring = (self.min - center)
+            .abs()
+            .max((self.max - center).abs())
+            .max_element();
+        for ring in 0..=farthest_ring {
+            for cell in ring_cells(center, ring) {
+                found.extend(self.cell(cell).iter().map(|&(entity, position)| {
+                    (position.distance_squared(point), entity, position)
+                }));
+            }
+            found.sort_by(|a, b| a.0.total_cmp(&b.0));
+            found.truncate(k);
+
+            // Everything in the rings further out is at least this far away.
+            let reach = ring as f32 * SPATIAL_CELL_SIZE;
+            if found.len() == k && found[k - 1].0 <= reach * reach {
+                break;
+            }
+        }
+        found
+            .into_iter()
+            .map(|(_, entity, position)| (entity, position))
+            .collect()
+    }
+}
+
+// The cells `ring` steps away from `center`, going around its edge.
+fn ring_cells(center: IVec2, ring: i32) -> Vec<IVec2> {
+    if ring == 0 {
+        return vec![center];
+    }
+    let mut cells = Vec::with_capacity(8 * ring as usize);
+    for x in -ring..=ring {
+        cells.push(center + IVec2::new(x, -ring));
+        cells.push(center + IVec2::new(x, ring));
+    }
+    for y in -ring + 1..ring {
+        cells.push(center + IVec2::new(-ring, y));
+        cells.push(center + IVec2::new(ring, y));
+    }
+    cells
+}
+
+fn update_index<F: QueryFilter + Send + Sync + 'static>(
+    mut index: ResMut<SpatialIndex<F>>,
+    entities: Query<(Entity, &Transform), F>,
+) {
+    index.clear();
+    for (entity, transform) in &entities {
+        index.insert(entity, transform.translation.xy());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::prelude::*;
+
+    fn scattered(count: usize, seed: u64) -> Vec<(Entity, Vec2)> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..count)
+            .map(|i| {
+                let position = Vec2::new(
+                    rng.random_range(-1500.0..1500.0),
+                    rng.random_range(-800.0..800.0),
+                );
+                (Entity::from_raw(i as u32), position)
+            })
+            .collect()
+    }
+
+    fn index_of(entities: &[(Entity, Vec2)]) -> SpatialIndex<()> {
+        let mut index = SpatialIndex::default();
+        for &(entity, position) in entities {
+            index.insert(entity, position);
+        }
+        index
+    }
+
+    fn nearest_by_scanning(entities: &[(Entity, Vec2)], point: Vec2, k: usize) -> Vec<Entity> {
+        let mut sorted = entities.to_vec();
+        sorted.sort_by(|a, b| {
+            a.1.distance_squared(point)
+                .total_cmp(&b.1.distance_squared(point))
+        });
+        sorted.iter().take(k).map(|(entity, _)| *entity).collect()
+    }
+
+    #[test]
+    fn lookups_match_scanning_everything() {
+        let entities = scattered(500, 1);
+        let index = index_of(&entities);
+
+        for point in [Vec2::ZERO, Vec2::new(1400., -700.), Vec2::new(5000., 5000.)] {
+            let nearest: Vec<Entity> = index
+                .nearest(point, 7)
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            assert_eq!(nearest, nearest_by_scanning(&entities, point, 7));
+
+            let mut within: Vec<Entity> = index
+                .within_radius(point, 250.)
+                .into_iter()
+                .map(|(entity, _)| entity)
+                .collect();
+            let mut expected: Vec<Entity> = entities
+                .iter()
+                .filter(|(_, position)| position.distance(point) <= 250.)
+                .map(|(entity, _)| *entity)
+                .collect();
+            within.sort();
+            expected.sort();
+            assert_eq!(within, expected);
+        }
+        assert!(SpatialIndex::<()>::default()
+            .nearest(Vec2::ZERO, 3)
+            .is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+// Small key/value persistence. Native builds write one file per key to the
+// working directory, the web build uses the browser's localStorage.
+
+use bevy::log::warn;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(key).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save(key: &str, contents: &str) {
+    if let Err(error) = std::fs::write(key, contents) {
+        warn!("Failed to save {key}: {error}");
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(key).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, contents: &str) {
+    let Some(storage) = local_storage() else {
+        warn!("localStorage is unavailable, {key} was not saved");
+        return;
+    };
+
+    if storage.set_item(key, contents).is_err() {
+        warn!("Failed to save {key} to localStorage");
+    }
+}
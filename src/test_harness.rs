@@ -0,0 +1,109 @@
+use avian2d::prelude::*;
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::*;
+use std::hash::Hash;
+
+use crate::archetype::{EnemyRegistry, VIRUS};
+use crate::enemy::create_enemy;
+use crate::headless::base_app;
+use crate::health::Health;
+use crate::host::{handle_infection, Host};
+use crate::level::{create_player, WallCellBundle};
+use crate::movement::{FlowSusceptibility, Inertia, Speed, Velocity};
+use crate::player::{handle_virus_collision, WhiteBloodCell, WhiteBloodCellBundle};
+use crate::rng::GameRng;
+
+// A headless app with every gameplay rule but no level, so tests can place
+// exactly the entities they care about. The run starts on the first update,
+// which must have at least one host around or it is lost straight away.
+pub fn test_app() -> App {
+    let mut app = base_app();
+    app.insert_resource(GameRng::from_seed(0));
+    // `App::run` would normally do this, physics finishes its setup here.
+    app.finish();
+    app.cleanup();
+    app
+}
+
+pub fn run_frames(app: &mut App, frames: usize) {
+    for _ in 0..frames {
+        app.update();
+    }
+}
+
+pub fn count<F: QueryFilter>(app: &mut App) -> usize {
+    app.world_mut()
+        .query_filtered::<Entity, F>()
+        .iter(app.world())
+        .count()
+}
+
+pub fn spawn_host(app: &mut App, position: Vec2) -> Entity {
+    app.world_mut()
+        .spawn((
+            WallCellBundle {
+                sprite: Sprite::default(),
+                transform: Transform::from_translation(position.extend(0.)),
+                collider: Collider::rectangle(35., 35.),
+                host: Host,
+            },
+            CollisionEventsEnabled,
+        ))
+        .observe(handle_infection)
+        .id()
+}
+
+pub fn spawn_virus(app: &mut App, position: Vec2, direction: Vec2) -> Entity {
+    spawn_enemy(app, VIRUS, position, direction)
+}
+
+pub fn spawn_enemy(app: &mut App, id: &str, position: Vec2, direction: Vec2) -> Entity {
+    app.world_mut()
+        .spawn(create_enemy(
+            &EnemyRegistry::default(),
+            None,
+            id,
+            direction,
+            position,
+        ))
+        .id()
+}
+
+pub fn spawn_white_blood_cell(app: &mut App, position: Vec2) -> Entity {
+    app.world_mut()
+        .spawn(WhiteBloodCellBundle {
+            marker: WhiteBloodCell,
+            sprite: Sprite::default(),
+            transform: Transform::from_translation(position.extend(0.)),
+            velocity: Velocity::new(Vec3::ZERO),
+            speed: Speed::new(0.),
+            inertia: Inertia::new(0., 0.),
+            flow_susceptibility: FlowSusceptibility(0.),
+            collider: Collider::circle(20.0),
+            health: Health::new(1.),
+            colliding_entities: CollidingEntities::default(),
+            collision_events: CollisionEventsEnabled,
+        })
+        .observe(handle_virus_collision)
+        .id()
+}
+
+pub fn spawn_player(app: &mut App) -> Entity {
+    app.world_mut()
+        .spawn(create_player(None))
+        .observe(handle_virus_collision)
+        .id()
+}
+
+// Works for keys and mouse buttons alike.
+pub fn press<T: Copy + Eq + Hash + Send + Sync + 'static>(app: &mut App, button: T) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<T>>()
+        .press(button);
+}
+
+pub fn release<T: Copy + Eq + Hash + Send + Sync + 'static>(app: &mut App, button: T) {
+    app.world_mut()
+        .resource_mut::<ButtonInput<T>>()
+        .release(button);
+}
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use crate::controls::{gather_player_input, InputDevice, TouchButtonState, TouchControls};
+use crate::schedule::GameState;
+
+pub struct TouchPlugin;
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_touch_controls);
+        app.add_systems(
+            RunFixedMainLoop,
+            read_touch_controls
+                .before(gather_player_input)
+                .in_set(RunFixedMainLoopSystem::BeforeFixedMainLoop),
+        );
+        app.add_systems(Update, (move_joystick_knob, show_touch_controls));
+    }
+}
+
+const JOYSTICK_SIZE: f32 = 140.0;
+const KNOB_SIZE: f32 = 56.0;
+const BUTTON_SIZE: f32 = 100.0;
+// Small wobbles of the thumb around the centre shouldn't move the player.
+const JOYSTICK_DEAD_ZONE: f32 = 0.2;
+
+#[derive(Component)]
+struct TouchOverlay;
+
+// Remembers which finger is on it, so sliding off doesn't let go.
+#[derive(Component, Default)]
+struct TouchJoystick {
+    touch: Option<u64>,
+}
+
+#[derive(Component)]
+struct JoystickKnob;
+
+#[derive(Component)]
+struct TouchButton {
+    action: TouchAction,
+    touch: Option<u64>,
+}
+
+#[derive(Clone, Copy)]
+enum TouchAction {
+    Boost,
+    Replicate,
+}
+
+fn touch_button(action: TouchAction, label: &str, right: f32) -> impl Bundle {
+    (
+        TouchButton {
+            action,
+            touch: None,
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            right: Val::Px(right),
+            bottom: Val::Px(40.0),
+            width: Val::Px(BUTTON_SIZE),
+            height: Val::Px(BUTTON_SIZE),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        BorderRadius::MAX,
+        BackgroundColor(Color::WHITE.with_alpha(0.2)),
+        children![Text::new(label)],
+    )
+}
+
+// Hidden until the player touches the screen.
+fn spawn_touch_controls(mut commands: Commands) {
+    commands.spawn((
+        TouchOverlay,
+        Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            ..default()
+        },
+        Visibility::Hidden,
+        children![
+            (
+                TouchJoystick::default(),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(30.0),
+                    bottom: Val::Px(130.0),
+                    width: Val::Px(JOYSTICK_SIZE),
+                    height: Val::Px(JOYSTICK_SIZE),
+                    ..default()
+                },
+                BorderRadius::MAX,
+                BackgroundColor(Color::WHITE.with_alpha(0.15)),
+                children![(
+                    JoystickKnob,
+                    Node {
+                        position_type: PositionType::Absolute,
+                        left: Val::Px((JOYSTICK_SIZE - KNOB_SIZE) / 2.0),
+                        top: Val::Px((JOYSTICK_SIZE - KNOB_SIZE) / 2.0),
+                        width: Val::Px(KNOB_SIZE),
+                        height: Val::Px(KNOB_SIZE),
+                        ..default()
+                    },
+                    BorderRadius::MAX,
+                    BackgroundColor(Color::WHITE.with_alpha(0.4)),
+                )],
+            ),
+            touch_button(TouchAction::Boost, "Boost", 30.0),
+            touch_button(TouchAction::Replicate, "Replicate", 150.0),
+        ],
+    ));
+}
+
+// UI nodes are laid out in physical pixels, touches come in logical ones.
+fn node_rect(node: &ComputedNode, transform: &GlobalTransform) -> Rect {
+    let scale = node.inverse_scale_factor();
+    Rect::from_center_size(
+        transform.translation().truncate() * scale,
+        node.size() * scale,
+    )
+}
+
+fn read_touch_controls(
+    touches: Res<Touches>,
+    mut controls: ResMut<TouchControls>,
+    joystick: Single<(&mut TouchJoystick, &ComputedNode, &GlobalTransform)>,
+    mut buttons: Query<(&mut TouchButton, &ComputedNode, &GlobalTransform)>,
+) {
+    let (mut joystick, joystick_node, joystick_transform) = joystick.into_inner();
+    let joystick_rect = node_rect(joystick_node, joystick_transform);
+
+    for touch in touches.iter_just_pressed() {
+        if joystick.touch.is_none() && joystick_rect.contains(touch.position()) {
+            joystick.touch = Some(touch.id());
+            continue;
+        }
+        for (mut button, node, transform) in &mut buttons {
+            if button.touch.is_none() && node_rect(node, transform).contains(touch.position()) {
+                button.touch = Some(touch.id());
+            }
+        }
+    }
+
+    controls.movement = match joystick.touch.and_then(|id| touches.get_pressed(id)) {
+        Some(touch) => {
+            // Screen y points down, the world's points up.
+            let offset = (touch.position() - joystick_rect.center()) / (JOYSTICK_SIZE / 2.0);
+            let tilt = Vec2::new(offset.x, -offset.y).clamp_length_max(1.0);
+            if tilt.length() < JOYSTICK_DEAD_ZONE {
+                Vec2::ZERO
+            } else {
+                tilt
+            }
+        }
+        None => {
+            joystick.touch = None;
+            Vec2::ZERO
+        }
+    };
+
+    for (mut button, _, _) in &mut buttons {
+        let held = button
+            .touch
+            .is_some_and(|id| touches.get_pressed(id).is_some());
+        let state = TouchButtonState {
+            held,
+            released: button.touch.is_some() && !held,
+        };
+        if !held {
+            button.touch = None;
+        }
+
+        match button.action {
+            TouchAction::Boost => controls.boost = state,
+            TouchAction::Replicate => controls.replicate = state,
+        }
+    }
+}
+
+fn move_joystick_knob(
+    controls: Res<TouchControls>,
+    mut knob: Single<&mut Node, With<JoystickKnob>>,
+) {
+    let travel = (JOYSTICK_SIZE - KNOB_SIZE) / 2.0;
+    knob.left = Val::Px(travel + controls.movement.x * travel);
+    knob.top = Val::Px(travel - controls.movement.y * travel);
+}
+
+fn show_touch_controls(
+    device: Res<InputDevice>,
+    state: Res<State<GameState>>,
+    mut overlay: Single<&mut Visibility, With<TouchOverlay>>,
+) {
+    let visible = *device == InputDevice::Touch && *state.get() == GameState::Playing;
+    overlay.set_if_neq(if visible {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    });
+}